@@ -0,0 +1,101 @@
+use std::collections::BTreeSet;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use test_tree::{ArenaAvlTreeSet, AvlTreeSet};
+
+// Boxed nodes against arena nodes, with the std BTreeSet as a baseline
+
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+
+// Same pseudo random sequence for every set, so they all do the same work
+fn values(count: usize) -> Vec<u32> {
+    let mut value = 1u32;
+
+    (0..count)
+        .map(|_| {
+            value = value.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            value
+        })
+        .collect()
+}
+
+fn insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert");
+
+    for &size in SIZES.iter() {
+        let values = values(size);
+
+        group.bench_with_input(BenchmarkId::new("boxed", size), &values, |b, values| {
+            b.iter(|| values.iter().copied().collect::<AvlTreeSet<_>>())
+        });
+        group.bench_with_input(BenchmarkId::new("arena", size), &values, |b, values| {
+            b.iter(|| values.iter().copied().collect::<ArenaAvlTreeSet<_>>())
+        });
+        group.bench_with_input(BenchmarkId::new("btree", size), &values, |b, values| {
+            b.iter(|| values.iter().copied().collect::<BTreeSet<_>>())
+        });
+    }
+
+    group.finish();
+}
+
+fn lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lookup");
+
+    for &size in SIZES.iter() {
+        let values = values(size);
+        let boxed: AvlTreeSet<_> = values.iter().copied().collect();
+        let arena: ArenaAvlTreeSet<_> = values.iter().copied().collect();
+        let btree: BTreeSet<_> = values.iter().copied().collect();
+
+        group.bench_with_input(BenchmarkId::new("boxed", size), &values, |b, values| {
+            b.iter(|| values.iter().filter(|value| boxed.contains(value)).count())
+        });
+        group.bench_with_input(BenchmarkId::new("arena", size), &values, |b, values| {
+            b.iter(|| values.iter().filter(|value| arena.contains(value)).count())
+        });
+        group.bench_with_input(BenchmarkId::new("btree", size), &values, |b, values| {
+            b.iter(|| values.iter().filter(|value| btree.contains(value)).count())
+        });
+    }
+
+    group.finish();
+}
+
+fn iterate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iterate");
+
+    for &size in SIZES.iter() {
+        let values = values(size);
+        let boxed: AvlTreeSet<_> = values.iter().copied().collect();
+        let arena: ArenaAvlTreeSet<_> = values.iter().copied().collect();
+        let btree: BTreeSet<_> = values.iter().copied().collect();
+
+        group.bench_function(BenchmarkId::new("boxed", size), |b| {
+            b.iter(|| {
+                black_box(&boxed)
+                    .iter()
+                    .fold(0u32, |sum, value| sum ^ value)
+            })
+        });
+        group.bench_function(BenchmarkId::new("arena", size), |b| {
+            b.iter(|| {
+                black_box(&arena)
+                    .iter()
+                    .fold(0u32, |sum, value| sum ^ value)
+            })
+        });
+        group.bench_function(BenchmarkId::new("btree", size), |b| {
+            b.iter(|| {
+                black_box(&btree)
+                    .iter()
+                    .fold(0u32, |sum, value| sum ^ value)
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, insert, lookup, iterate);
+criterion_main!(benches);
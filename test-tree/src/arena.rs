@@ -0,0 +1,1655 @@
+use std::borrow::Borrow;
+use std::cmp::{max, Ordering};
+use std::fmt::{self, Debug};
+use std::iter::FromIterator;
+use std::mem::{replace, swap};
+use std::ops::RangeBounds;
+use std::vec;
+
+use crate::compare::{Compare, Natural};
+use crate::node::{is_balanced, structural_eq, NodeView};
+use crate::set::{to_dot, to_pretty_string};
+#[cfg(debug_assertions)]
+use crate::set::{validate_tree, InvariantError};
+
+mod bulk;
+mod cursor;
+mod extract;
+mod ops;
+mod par;
+
+pub use cursor::{ArenaCursor, ArenaCursorMut};
+pub use extract::ArenaExtractIf;
+pub use par::ArenaAvlTreeSetParIter;
+
+// Variant of `AvlTreeSet` that keeps all nodes in one `Vec` and links them by
+// `u32` indices instead of boxing every node. Removed nodes leave their slot
+// on a free list, so the next insert reuses it instead of growing the arena.
+//
+// Every set has an arena of its own. Operations that bring two sets together,
+// `append`, `join` and the consuming set operations, first move the nodes of
+// the smaller arena into the larger one, and `split_off` moves the smaller
+// part out into a new arena. On top of what the boxed set takes, that costs
+// O(k) for the k nodes moved.
+
+// Index of a missing child, and of the end of the free list
+const NIL: u32 = u32::MAX;
+
+#[derive(Debug, Clone)]
+struct ArenaNode<T> {
+    value: T,
+    left: u32,
+    right: u32,
+    height: u32,
+    size: u32,
+}
+
+#[derive(Debug, Clone)]
+enum Slot<T> {
+    Node(ArenaNode<T>),
+    // A freed slot linking to the next free one
+    Free(u32),
+}
+
+fn node<T>(slots: &[Slot<T>], index: u32) -> &ArenaNode<T> {
+    match &slots[index as usize] {
+        Slot::Node(node) => node,
+        Slot::Free(_) => unreachable!("free arena slot is linked into the tree"),
+    }
+}
+
+fn node_mut<T>(slots: &mut [Slot<T>], index: u32) -> &mut ArenaNode<T> {
+    match &mut slots[index as usize] {
+        Slot::Node(node) => node,
+        Slot::Free(_) => unreachable!("free arena slot is linked into the tree"),
+    }
+}
+
+// A node together with the slots its children are in
+struct SlotRef<'a, T> {
+    slots: &'a [Slot<T>],
+    index: u32,
+}
+
+impl<'a, T> Clone for SlotRef<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for SlotRef<'a, T> {}
+
+impl<'a, T> SlotRef<'a, T> {
+    fn new(slots: &'a [Slot<T>], index: u32) -> Option<Self> {
+        if index == NIL {
+            None
+        } else {
+            Some(Self { slots, index })
+        }
+    }
+}
+
+impl<'a, T> NodeView<'a> for SlotRef<'a, T> {
+    type Value = T;
+
+    fn value(self) -> &'a T {
+        &node(self.slots, self.index).value
+    }
+
+    fn children(self) -> (Option<Self>, Option<Self>) {
+        let current_node = node(self.slots, self.index);
+
+        (
+            Self::new(self.slots, current_node.left),
+            Self::new(self.slots, current_node.right),
+        )
+    }
+
+    fn cached_height(self) -> usize {
+        node(self.slots, self.index).height as usize
+    }
+
+    fn cached_size(self) -> usize {
+        node(self.slots, self.index).size as usize
+    }
+}
+
+// The node storage, kept apart from the comparator so that search closures can
+// borrow the comparator while the nodes are being relinked
+#[derive(Debug, Clone)]
+struct Arena<T> {
+    slots: Vec<Slot<T>>,
+    free: u32,
+}
+
+// Dropping and cloning the arena does not recurse, so unlike the boxed set
+// neither needs a manual implementation
+#[derive(Clone)]
+pub struct ArenaAvlTreeSet<T, C = Natural> {
+    arena: Arena<T>,
+    root: u32,
+    cmp: C,
+}
+
+impl<T: Debug, C: Compare<T>> Debug for ArenaAvlTreeSet<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T, C: Compare<T> + Default> Default for ArenaAvlTreeSet<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T: Ord> ArenaAvlTreeSet<T> {
+    pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut set = Self::new();
+        set.arena.slots.reserve(capacity);
+
+        set
+    }
+}
+
+// Every operation works on node indices and returns the index of the node that
+// ends up at the root of the given subtree, the caller links it into the parent
+impl<T> Arena<T> {
+    fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: NIL,
+        }
+    }
+
+    fn node(&self, index: u32) -> &ArenaNode<T> {
+        node(&self.slots, index)
+    }
+
+    fn node_mut(&mut self, index: u32) -> &mut ArenaNode<T> {
+        node_mut(&mut self.slots, index)
+    }
+
+    fn height_of(&self, index: u32) -> u32 {
+        if index == NIL {
+            0
+        } else {
+            self.node(index).height
+        }
+    }
+
+    fn size_of(&self, index: u32) -> u32 {
+        if index == NIL {
+            0
+        } else {
+            self.node(index).size
+        }
+    }
+
+    fn update(&mut self, index: u32) {
+        let (left, right) = (self.node(index).left, self.node(index).right);
+        let height = 1 + max(self.height_of(left), self.height_of(right));
+        let size = 1 + self.size_of(left) + self.size_of(right);
+
+        let node = self.node_mut(index);
+        node.height = height;
+        node.size = size;
+    }
+
+    fn balance_factor(&self, index: u32) -> i64 {
+        let node = self.node(index);
+
+        i64::from(self.height_of(node.left)) - i64::from(self.height_of(node.right))
+    }
+
+    fn rotate_right(&mut self, index: u32) -> u32 {
+        let pivot = self.node(index).left;
+        let inner = self.node(pivot).right;
+
+        self.node_mut(index).left = inner;
+        self.node_mut(pivot).right = index;
+        self.update(index);
+        self.update(pivot);
+
+        pivot
+    }
+
+    fn rotate_left(&mut self, index: u32) -> u32 {
+        let pivot = self.node(index).right;
+        let inner = self.node(pivot).left;
+
+        self.node_mut(index).right = inner;
+        self.node_mut(pivot).left = index;
+        self.update(index);
+        self.update(pivot);
+
+        pivot
+    }
+
+    fn rebalance(&mut self, index: u32) -> u32 {
+        self.update(index);
+
+        let balance_factor = self.balance_factor(index);
+
+        if balance_factor > 1 {
+            let left = self.node(index).left;
+
+            if self.balance_factor(left) < 0 {
+                let left = self.rotate_left(left);
+                self.node_mut(index).left = left;
+            }
+
+            self.rotate_right(index)
+        } else if balance_factor < -1 {
+            let right = self.node(index).right;
+
+            if self.balance_factor(right) > 0 {
+                let right = self.rotate_right(right);
+                self.node_mut(index).right = right;
+            }
+
+            self.rotate_left(index)
+        } else {
+            index
+        }
+    }
+
+    fn alloc(&mut self, value: T) -> u32 {
+        self.alloc_node(ArenaNode {
+            value,
+            left: NIL,
+            right: NIL,
+            height: 1,
+            size: 1,
+        })
+    }
+
+    fn alloc_node(&mut self, node: ArenaNode<T>) -> u32 {
+        let node = Slot::Node(node);
+
+        if self.free == NIL {
+            assert!(
+                self.slots.len() < NIL as usize,
+                "ArenaAvlTreeSet cannot hold more than u32::MAX - 1 values"
+            );
+
+            self.slots.push(node);
+
+            (self.slots.len() - 1) as u32
+        } else {
+            let index = self.free;
+
+            self.free = match replace(&mut self.slots[index as usize], node) {
+                Slot::Free(next_free) => next_free,
+                Slot::Node(_) => unreachable!("free list links to a used arena slot"),
+            };
+
+            index
+        }
+    }
+
+    fn release(&mut self, index: u32) -> T {
+        self.release_node(index).value
+    }
+
+    fn release_node(&mut self, index: u32) -> ArenaNode<T> {
+        match replace(&mut self.slots[index as usize], Slot::Free(self.free)) {
+            Slot::Node(node) => {
+                self.free = index;
+
+                node
+            }
+            Slot::Free(_) => unreachable!("arena slot is released twice"),
+        }
+    }
+
+    // Drops the values of the whole subtree and frees their slots
+    fn release_tree(&mut self, index: u32) {
+        let mut nodes = vec![index];
+
+        while let Some(index) = nodes.pop() {
+            if index != NIL {
+                let released = self.release_node(index);
+                nodes.push(released.left);
+                nodes.push(released.right);
+            }
+        }
+    }
+
+    // Moves the subtree into the other arena and returns its root there. The
+    // recursion goes only as deep as the tree is high.
+    fn move_tree(&mut self, index: u32, target: &mut Self) -> u32 {
+        if index == NIL {
+            return NIL;
+        }
+
+        let mut moved = self.release_node(index);
+        moved.left = self.move_tree(moved.left, target);
+        moved.right = self.move_tree(moved.right, target);
+
+        target.alloc_node(moved)
+    }
+
+    // Unlinks the least node of the subtree, returning the new subtree root
+    // and the unlinked node
+    fn unlink_first(&mut self, index: u32) -> (u32, u32) {
+        let left = self.node(index).left;
+
+        if left == NIL {
+            return (self.node(index).right, index);
+        }
+
+        let (left, first) = self.unlink_first(left);
+        self.node_mut(index).left = left;
+
+        (self.rebalance(index), first)
+    }
+
+    fn unlink_last(&mut self, index: u32) -> (u32, u32) {
+        let right = self.node(index).right;
+
+        if right == NIL {
+            return (self.node(index).left, index);
+        }
+
+        let (right, last) = self.unlink_last(right);
+        self.node_mut(index).right = right;
+
+        (self.rebalance(index), last)
+    }
+
+    // Unlinks the subtree root, returning the node that replaces it. The
+    // successor node is moved into its place, the values stay where they are.
+    fn unlink_root(&mut self, index: u32) -> u32 {
+        let (left, right) = (self.node(index).left, self.node(index).right);
+
+        if left == NIL {
+            return right;
+        }
+
+        if right == NIL {
+            return left;
+        }
+
+        let (right, successor) = self.unlink_first(right);
+        let successor_node = self.node_mut(successor);
+        successor_node.left = left;
+        successor_node.right = right;
+
+        self.rebalance(successor)
+    }
+
+    fn take_by<F>(&mut self, index: u32, f: &mut F) -> (u32, Option<T>)
+    where
+        F: FnMut(&T) -> Ordering,
+    {
+        if index == NIL {
+            return (NIL, None);
+        }
+
+        let taken = match f(&self.node(index).value) {
+            Ordering::Greater => {
+                let (left, taken) = self.take_by(self.node(index).left, f);
+                self.node_mut(index).left = left;
+                taken
+            }
+            Ordering::Less => {
+                let (right, taken) = self.take_by(self.node(index).right, f);
+                self.node_mut(index).right = right;
+                taken
+            }
+            Ordering::Equal => {
+                let replacement = self.unlink_root(index);
+
+                return (replacement, Some(self.release(index)));
+            }
+        };
+
+        match taken {
+            Some(_) => (self.rebalance(index), taken),
+            None => (index, None),
+        }
+    }
+
+    fn take_at(&mut self, index: u32, position: usize) -> (u32, Option<T>) {
+        if index == NIL {
+            return (NIL, None);
+        }
+
+        let left_size = self.size_of(self.node(index).left) as usize;
+
+        let taken = match position.cmp(&left_size) {
+            Ordering::Less => {
+                let (left, taken) = self.take_at(self.node(index).left, position);
+                self.node_mut(index).left = left;
+                taken
+            }
+            Ordering::Greater => {
+                let right = self.node(index).right;
+                let (right, taken) = self.take_at(right, position - left_size - 1);
+                self.node_mut(index).right = right;
+                taken
+            }
+            Ordering::Equal => {
+                let replacement = self.unlink_root(index);
+
+                return (replacement, Some(self.release(index)));
+            }
+        };
+
+        match taken {
+            Some(_) => (self.rebalance(index), taken),
+            None => (index, None),
+        }
+    }
+
+    fn insert_at<F>(&mut self, index: u32, value: T, cmp: &mut F) -> (u32, bool)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        if index == NIL {
+            return (self.alloc(value), true);
+        }
+
+        let inserted = match cmp(&value, &self.node(index).value) {
+            Ordering::Less => {
+                let (left, inserted) = self.insert_at(self.node(index).left, value, cmp);
+                self.node_mut(index).left = left;
+                inserted
+            }
+            Ordering::Greater => {
+                let (right, inserted) = self.insert_at(self.node(index).right, value, cmp);
+                self.node_mut(index).right = right;
+                inserted
+            }
+            Ordering::Equal => false,
+        };
+
+        if inserted {
+            (self.rebalance(index), true)
+        } else {
+            (index, false)
+        }
+    }
+
+    fn first(&self, mut index: u32) -> Option<&T> {
+        let mut first = None;
+
+        while index != NIL {
+            let current_node = self.node(index);
+            first = Some(&current_node.value);
+            index = current_node.left;
+        }
+
+        first
+    }
+
+    fn last(&self, mut index: u32) -> Option<&T> {
+        let mut last = None;
+
+        while index != NIL {
+            let current_node = self.node(index);
+            last = Some(&current_node.value);
+            index = current_node.right;
+        }
+
+        last
+    }
+
+    fn find_by<F>(&self, mut index: u32, mut f: F) -> Option<&T>
+    where
+        F: FnMut(&T) -> Ordering,
+    {
+        while index != NIL {
+            let current_node = self.node(index);
+
+            index = match f(&current_node.value) {
+                Ordering::Greater => current_node.left,
+                Ordering::Less => current_node.right,
+                Ordering::Equal => return Some(&current_node.value),
+            };
+        }
+
+        None
+    }
+
+    fn partition_point<F>(&self, mut index: u32, mut pred: F) -> usize
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut count = 0;
+
+        while index != NIL {
+            let current_node = self.node(index);
+
+            index = if pred(&current_node.value) {
+                count += self.size_of(current_node.left) as usize + 1;
+                current_node.right
+            } else {
+                current_node.left
+            };
+        }
+
+        count
+    }
+
+    fn partition_bounds<F>(&self, mut index: u32, mut pred: F) -> (Option<&T>, Option<&T>)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let (mut lower, mut upper) = (None, None);
+
+        while index != NIL {
+            let current_node = self.node(index);
+
+            index = if pred(&current_node.value) {
+                lower = Some(&current_node.value);
+                current_node.right
+            } else {
+                upper = Some(&current_node.value);
+                current_node.left
+            };
+        }
+
+        (lower, upper)
+    }
+
+    // Joins two trees with a pivot node that lies between all values of the
+    // left tree and all values of the right one, like `node::join` does
+    fn join(&mut self, left: u32, pivot: u32, right: u32) -> u32 {
+        let (left_height, right_height) = (self.height_of(left), self.height_of(right));
+
+        if left_height > right_height + 1 {
+            self.join_right(left, pivot, right)
+        } else if right_height > left_height + 1 {
+            self.join_left(left, pivot, right)
+        } else {
+            let pivot_node = self.node_mut(pivot);
+            pivot_node.left = left;
+            pivot_node.right = right;
+            self.update(pivot);
+
+            pivot
+        }
+    }
+
+    fn join_right(&mut self, left: u32, pivot: u32, right: u32) -> u32 {
+        let inner = self.node(left).right;
+
+        let inner = if self.height_of(inner) <= self.height_of(right) + 1 {
+            self.join(inner, pivot, right)
+        } else {
+            self.join_right(inner, pivot, right)
+        };
+        self.node_mut(left).right = inner;
+
+        self.rebalance(left)
+    }
+
+    fn join_left(&mut self, left: u32, pivot: u32, right: u32) -> u32 {
+        let inner = self.node(right).left;
+
+        let inner = if self.height_of(inner) <= self.height_of(left) + 1 {
+            self.join(left, pivot, inner)
+        } else {
+            self.join_left(left, pivot, inner)
+        };
+        self.node_mut(right).left = inner;
+
+        self.rebalance(right)
+    }
+
+    fn join_trees(&mut self, left: u32, right: u32) -> u32 {
+        if right == NIL {
+            return left;
+        }
+
+        let (right, pivot) = self.unlink_first(right);
+
+        self.join(left, pivot, right)
+    }
+
+    // Builds a perfectly balanced tree out of the next len values, like
+    // `node::build_sorted` does. Running out of values early keeps the ones
+    // built so far.
+    fn build_sorted<I>(&mut self, values: &mut I, len: usize) -> u32
+    where
+        I: Iterator<Item = T>,
+    {
+        if len == 0 {
+            return NIL;
+        }
+
+        let left = self.build_sorted(values, len / 2);
+        let index = match values.next() {
+            Some(value) => self.alloc(value),
+            None => return left,
+        };
+        let right = self.build_sorted(values, len - len / 2 - 1);
+
+        self.join(left, index, right)
+    }
+
+    // Splits the tree into the nodes before the searched one, the node itself
+    // or NIL, and the nodes after it. The closure gets the arena along with
+    // the node to compare, so that it can compare against a node of another
+    // tree in the same arena.
+    fn split<F>(&mut self, index: u32, f: &mut F) -> (u32, u32, u32)
+    where
+        F: FnMut(&Self, u32) -> Ordering,
+    {
+        if index == NIL {
+            return (NIL, NIL, NIL);
+        }
+
+        let (left, right) = (self.node(index).left, self.node(index).right);
+
+        match f(self, index) {
+            Ordering::Less => {
+                let (right_left, found, right_right) = self.split(right, f);
+
+                (self.join(left, index, right_left), found, right_right)
+            }
+
+            Ordering::Equal => (left, index, right),
+
+            Ordering::Greater => {
+                let (left_left, found, left_right) = self.split(left, f);
+
+                (left_left, found, self.join(left_right, index, right))
+            }
+        }
+    }
+
+    // The set operations work like the ones in `node` on two trees of the same
+    // arena. The nodes whose values get dropped are freed.
+
+    fn union<C>(&mut self, index: u32, other: u32, cmp: &mut C) -> u32
+    where
+        C: FnMut(&T, &T) -> Ordering,
+    {
+        if index == NIL {
+            return other;
+        }
+
+        if other == NIL {
+            return index;
+        }
+
+        let (left, right) = (self.node(index).left, self.node(index).right);
+
+        // Values of the first tree win over equal values of the other one
+        let (other_left, found, other_right) = self.split(other, &mut |arena, other_index| {
+            cmp(&arena.node(other_index).value, &arena.node(index).value)
+        });
+
+        if found != NIL {
+            self.release(found);
+        }
+
+        let left = self.union(left, other_left, cmp);
+        let right = self.union(right, other_right, cmp);
+
+        self.join(left, index, right)
+    }
+
+    fn intersection<C>(&mut self, index: u32, other: u32, cmp: &mut C) -> u32
+    where
+        C: FnMut(&T, &T) -> Ordering,
+    {
+        if index == NIL || other == NIL {
+            self.release_tree(index);
+            self.release_tree(other);
+
+            return NIL;
+        }
+
+        let (left, right) = (self.node(index).left, self.node(index).right);
+
+        let (other_left, found, other_right) = self.split(other, &mut |arena, other_index| {
+            cmp(&arena.node(other_index).value, &arena.node(index).value)
+        });
+        let left = self.intersection(left, other_left, cmp);
+        let right = self.intersection(right, other_right, cmp);
+
+        if found != NIL {
+            self.release(found);
+
+            self.join(left, index, right)
+        } else {
+            self.release(index);
+
+            self.join_trees(left, right)
+        }
+    }
+
+    fn difference<C>(&mut self, index: u32, other: u32, cmp: &mut C) -> u32
+    where
+        C: FnMut(&T, &T) -> Ordering,
+    {
+        if index == NIL {
+            self.release_tree(other);
+
+            return NIL;
+        }
+
+        if other == NIL {
+            return index;
+        }
+
+        let (left, right) = (self.node(other).left, self.node(other).right);
+
+        // Split the first tree by the removed value and drop it if present
+        let (tree_left, found, tree_right) = self.split(index, &mut |arena, tree_index| {
+            cmp(&arena.node(tree_index).value, &arena.node(other).value)
+        });
+
+        if found != NIL {
+            self.release(found);
+        }
+
+        self.release(other);
+
+        let left = self.difference(tree_left, left, cmp);
+        let right = self.difference(tree_right, right, cmp);
+
+        self.join_trees(left, right)
+    }
+
+    fn symmetric_difference<C>(&mut self, index: u32, other: u32, cmp: &mut C) -> u32
+    where
+        C: FnMut(&T, &T) -> Ordering,
+    {
+        if index == NIL {
+            return other;
+        }
+
+        if other == NIL {
+            return index;
+        }
+
+        let (left, right) = (self.node(index).left, self.node(index).right);
+
+        let (other_left, found, other_right) = self.split(other, &mut |arena, other_index| {
+            cmp(&arena.node(other_index).value, &arena.node(index).value)
+        });
+        let left = self.symmetric_difference(left, other_left, cmp);
+        let right = self.symmetric_difference(right, other_right, cmp);
+
+        // Values present in both trees are dropped
+        if found != NIL {
+            self.release(found);
+            self.release(index);
+
+            self.join_trees(left, right)
+        } else {
+            self.join(left, index, right)
+        }
+    }
+}
+
+impl<T, C: Compare<T>> ArenaAvlTreeSet<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        Self {
+            arena: Arena::new(),
+            root: NIL,
+            cmp,
+        }
+    }
+
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
+    pub fn insert(&mut self, value: T) -> bool {
+        let cmp = &self.cmp;
+        let (root, inserted) = self
+            .arena
+            .insert_at(self.root, value, &mut |a: &T, b: &T| cmp.compare(a, b));
+        self.root = root;
+
+        inserted
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.get(value).is_some()
+    }
+
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.arena.find_by(self.root, |current_value| {
+            self.cmp.compare(current_value.borrow(), value)
+        })
+    }
+
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.take(value).is_some()
+    }
+
+    pub fn take<Q>(&mut self, value: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let cmp = &self.cmp;
+        let (root, taken) = self.arena.take_by(self.root, &mut |current_value: &T| {
+            cmp.compare(current_value.borrow(), value)
+        });
+        self.root = root;
+
+        taken
+    }
+
+    pub fn pop_first(&mut self) -> Option<T> {
+        if self.root == NIL {
+            return None;
+        }
+
+        let (root, first) = self.arena.unlink_first(self.root);
+        self.root = root;
+
+        Some(self.arena.release(first))
+    }
+
+    pub fn pop_last(&mut self) -> Option<T> {
+        if self.root == NIL {
+            return None;
+        }
+
+        let (root, last) = self.arena.unlink_last(self.root);
+        self.root = root;
+
+        Some(self.arena.release(last))
+    }
+
+    // Moves all values greater than or equal to the given one into a new set
+    pub fn split_off<Q>(&mut self, value: &Q) -> Self
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q> + Clone,
+    {
+        let cmp = &self.cmp;
+        let (left, found, right) = self.arena.split(self.root, &mut |arena, index| {
+            cmp.compare(arena.node(index).value.borrow(), value)
+        });
+        let right = match found {
+            NIL => right,
+            found => self.arena.join(NIL, found, right),
+        };
+
+        let mut other = Self::with_comparator(self.cmp.clone());
+
+        // Only the smaller part moves, the larger one keeps its arena
+        if self.arena.size_of(left) < self.arena.size_of(right) {
+            self.root = self.arena.move_tree(left, &mut other.arena);
+            other.root = right;
+            swap(&mut self.arena, &mut other.arena);
+        } else {
+            self.root = left;
+            other.root = self.arena.move_tree(right, &mut other.arena);
+        }
+
+        other
+    }
+
+    // Moves the nodes of both sets into the larger of their arenas, which this
+    // set keeps, and returns the roots of both trees in it
+    fn merge_arenas(&mut self, other: &mut Self) -> (u32, u32) {
+        let mut other_arena = replace(&mut other.arena, Arena::new());
+        let other_root = replace(&mut other.root, NIL);
+        let root = replace(&mut self.root, NIL);
+
+        if other_arena.slots.len() > self.arena.slots.len() {
+            swap(&mut self.arena, &mut other_arena);
+
+            (other_arena.move_tree(root, &mut self.arena), other_root)
+        } else {
+            (root, other_arena.move_tree(other_root, &mut self.arena))
+        }
+    }
+
+    // Moves all values of the other set into this one. Sets that do not overlap
+    // are joined in O(log n), overlapping ones fall back to a union.
+    pub fn append(&mut self, other: &mut Self) {
+        let cmp = &self.cmp;
+
+        let is_before = match (self.last(), other.first()) {
+            (Some(last_value), Some(first_value)) => {
+                cmp.compare(last_value, first_value) == Ordering::Less
+            }
+            _ => true,
+        };
+        let is_after = match (self.first(), other.last()) {
+            (Some(first_value), Some(last_value)) => {
+                cmp.compare(first_value, last_value) == Ordering::Greater
+            }
+            _ => true,
+        };
+
+        let (tree, other_tree) = self.merge_arenas(other);
+        let cmp = &self.cmp;
+
+        self.root = if is_before {
+            self.arena.join_trees(tree, other_tree)
+        } else if is_after {
+            self.arena.join_trees(other_tree, tree)
+        } else {
+            self.arena
+                .union(tree, other_tree, &mut |a: &T, b: &T| cmp.compare(a, b))
+        };
+    }
+
+    // Builds a set out of a pivot and two sets with only smaller and only
+    // greater values in O(|left.height() - right.height()|). The joined set
+    // keeps the comparator of the left one.
+    pub fn join(mut left: Self, pivot: T, mut right: Self) -> Self {
+        assert!(
+            left.last()
+                .is_none_or(|value| left.cmp.compare(value, &pivot) == Ordering::Less)
+                && right
+                    .first()
+                    .is_none_or(|value| left.cmp.compare(value, &pivot) == Ordering::Greater),
+            "joined sets must be ordered around the pivot in ArenaAvlTreeSet"
+        );
+
+        let (tree, right_tree) = left.merge_arenas(&mut right);
+        let pivot = left.arena.alloc(pivot);
+        left.root = left.arena.join(tree, pivot, right_tree);
+
+        left
+    }
+
+    pub fn clear(&mut self) {
+        self.arena.slots.clear();
+        self.arena.free = NIL;
+        self.root = NIL;
+    }
+
+    pub fn len(&self) -> usize {
+        self.arena.size_of(self.root) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root == NIL
+    }
+
+    // The value at the given position in sorted order
+    pub fn select(&self, mut index: usize) -> Option<&T> {
+        let mut current_index = self.root;
+
+        while current_index != NIL {
+            let current_node = self.arena.node(current_index);
+            let left_size = self.arena.size_of(current_node.left) as usize;
+
+            current_index = match index.cmp(&left_size) {
+                Ordering::Less => current_node.left,
+                Ordering::Equal => return Some(&current_node.value),
+                Ordering::Greater => {
+                    index -= left_size + 1;
+                    current_node.right
+                }
+            };
+        }
+
+        None
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.arena.first(self.root)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.arena.last(self.root)
+    }
+
+    pub fn height(&self) -> usize {
+        self.arena.height_of(self.root) as usize
+    }
+
+    // Equality compares the values only, this also compares the shape of the
+    // trees, which depends on the order the values were inserted in
+    pub fn structural_eq(&self, other: &Self) -> bool
+    where
+        T: PartialEq,
+    {
+        structural_eq(
+            SlotRef::new(&self.arena.slots, self.root),
+            SlotRef::new(&other.arena.slots, other.root),
+        )
+    }
+
+    // Like `AvlTreeSet::validate`, only compiled into debug builds
+    #[cfg(debug_assertions)]
+    pub fn validate(&self) -> Result<(), InvariantError> {
+        validate_tree(
+            SlotRef::new(&self.arena.slots, self.root),
+            &self.cmp,
+            "ArenaAvlTreeSet",
+        )
+    }
+
+    // Checks the balance and the cached heights and sizes like the boxed set
+    // does, and also that every slot is either in the tree or on the free list
+    pub fn is_balanced(&self) -> bool {
+        if !is_balanced(SlotRef::new(&self.arena.slots, self.root)) {
+            return false;
+        }
+
+        let mut free = 0;
+        let mut index = self.arena.free;
+
+        while index != NIL {
+            index = match self.arena.slots[index as usize] {
+                Slot::Free(next_free) => next_free,
+                Slot::Node(_) => return false,
+            };
+            free += 1;
+        }
+
+        self.len() + free == self.arena.slots.len()
+    }
+
+    pub fn iter(&self) -> ArenaAvlTreeSetIter<'_, T> {
+        ArenaAvlTreeSetIter::new(&self.arena.slots, self.root, 0, self.len())
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> ArenaAvlTreeSetIter<'_, T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        R: RangeBounds<Q>,
+    {
+        let (front, back) = self.range_positions(&range);
+
+        ArenaAvlTreeSetIter::new(&self.arena.slots, self.root, front, back)
+    }
+}
+
+impl_sorted_lookups!(ArenaAvlTreeSet);
+impl_value_order!(ArenaAvlTreeSet);
+
+impl<T, C> ArenaAvlTreeSet<T, C> {
+    fn partition_point<F: FnMut(&T) -> bool>(&self, pred: F) -> usize {
+        self.arena.partition_point(self.root, pred)
+    }
+
+    fn partition_bounds<F: FnMut(&T) -> bool>(&self, pred: F) -> (Option<&T>, Option<&T>) {
+        self.arena.partition_bounds(self.root, pred)
+    }
+}
+
+impl<T: Debug, C> ArenaAvlTreeSet<T, C> {
+    pub fn to_dot(&self) -> String {
+        to_dot(
+            SlotRef::new(&self.arena.slots, self.root),
+            "ArenaAvlTreeSet",
+        )
+    }
+
+    pub fn to_pretty_string(&self) -> String {
+        to_pretty_string(SlotRef::new(&self.arena.slots, self.root))
+    }
+
+    pub fn pretty_print(&self) {
+        print!("{}", self.to_pretty_string());
+    }
+}
+
+// Both ends keep a stack of the nodes whose subtree on the inner side has not
+// been visited yet, together with their position in the whole tree, so that
+// skipping ahead seeks from the root like the boxed iterator does
+pub struct ArenaAvlTreeSetIter<'a, T> {
+    slots: &'a [Slot<T>],
+    root: u32,
+    front_nodes: Vec<u32>,
+    back_nodes: Vec<u32>,
+    front: usize,
+    back: usize,
+}
+
+impl<'a, T> Clone for ArenaAvlTreeSetIter<'a, T> {
+    fn clone(&self) -> Self {
+        Self {
+            slots: self.slots,
+            root: self.root,
+            front_nodes: self.front_nodes.clone(),
+            back_nodes: self.back_nodes.clone(),
+            front: self.front,
+            back: self.back,
+        }
+    }
+}
+
+// Iterators print the values they have left rather than the whole arena
+impl<'a, T: Debug> Debug for ArenaAvlTreeSetIter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, T> ArenaAvlTreeSetIter<'a, T> {
+    fn new(slots: &'a [Slot<T>], root: u32, front: usize, back: usize) -> Self {
+        let mut iter = Self {
+            slots,
+            root,
+            front_nodes: Vec::new(),
+            back_nodes: Vec::new(),
+            front,
+            back: max(front, back),
+        };
+
+        iter.seek_front();
+        iter.seek_back();
+
+        iter
+    }
+
+    fn size_of(&self, index: u32) -> usize {
+        if index == NIL {
+            0
+        } else {
+            node(self.slots, index).size as usize
+        }
+    }
+
+    // Rebuilds the front stack so that its top is the value at the front position
+    fn seek_front(&mut self) {
+        self.front_nodes.clear();
+
+        if self.front == self.back {
+            return;
+        }
+
+        let mut position = self.front;
+        let mut index = self.root;
+
+        while index != NIL {
+            let current_node = node(self.slots, index);
+            let left_size = self.size_of(current_node.left);
+
+            if position <= left_size {
+                self.front_nodes.push(index);
+
+                if position == left_size {
+                    return;
+                }
+
+                index = current_node.left;
+            } else {
+                position -= left_size + 1;
+                index = current_node.right;
+            }
+        }
+    }
+
+    // Rebuilds the back stack so that its top is the value before the back position
+    fn seek_back(&mut self) {
+        self.back_nodes.clear();
+
+        if self.front == self.back {
+            return;
+        }
+
+        let mut position = self.back - 1;
+        let mut index = self.root;
+
+        while index != NIL {
+            let current_node = node(self.slots, index);
+            let left_size = self.size_of(current_node.left);
+
+            if position >= left_size {
+                self.back_nodes.push(index);
+
+                if position == left_size {
+                    return;
+                }
+
+                position -= left_size + 1;
+                index = current_node.right;
+            } else {
+                index = current_node.left;
+            }
+        }
+    }
+
+    fn push_left(&mut self, mut index: u32) {
+        while index != NIL {
+            self.front_nodes.push(index);
+            index = node(self.slots, index).left;
+        }
+    }
+
+    fn push_right(&mut self, mut index: u32) {
+        while index != NIL {
+            self.back_nodes.push(index);
+            index = node(self.slots, index).right;
+        }
+    }
+}
+
+impl<'a, T> Iterator for ArenaAvlTreeSetIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        let current_node = node(self.slots, self.front_nodes.pop()?);
+        self.push_left(current_node.right);
+        self.front += 1;
+
+        Some(&current_node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+
+        (len, Some(len))
+    }
+
+    // Skipping seeks from the root in O(log n) instead of popping one by one
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if n > 0 {
+            self.front = if n < self.len() {
+                self.front + n
+            } else {
+                self.back
+            };
+            self.seek_front();
+        }
+
+        self.next()
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for ArenaAvlTreeSetIter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        let current_node = node(self.slots, self.back_nodes.pop()?);
+        self.push_right(current_node.left);
+        self.back -= 1;
+
+        Some(&current_node.value)
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+        if n > 0 {
+            self.back = if n < self.len() {
+                self.back - n
+            } else {
+                self.front
+            };
+            self.seek_back();
+        }
+
+        self.next_back()
+    }
+}
+
+impl<'a, T> ExactSizeIterator for ArenaAvlTreeSetIter<'a, T> {}
+
+// Takes the values out of the arena slots in the order of the tree
+pub struct ArenaAvlTreeSetIntoIter<T> {
+    slots: Vec<Slot<T>>,
+    order: vec::IntoIter<u32>,
+}
+
+impl<T: Debug> Debug for ArenaAvlTreeSetIntoIter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values = self
+            .order
+            .as_slice()
+            .iter()
+            .map(|&index| &node(&self.slots, index).value);
+
+        f.debug_list().entries(values).finish()
+    }
+}
+
+impl<T> ArenaAvlTreeSetIntoIter<T> {
+    fn new(arena: Arena<T>, root: u32) -> Self {
+        let mut order = Vec::with_capacity(arena.size_of(root) as usize);
+        let mut prev_nodes = Vec::new();
+        let mut index = root;
+
+        while index != NIL || !prev_nodes.is_empty() {
+            while index != NIL {
+                prev_nodes.push(index);
+                index = arena.node(index).left;
+            }
+
+            if let Some(prev_index) = prev_nodes.pop() {
+                order.push(prev_index);
+                index = arena.node(prev_index).right;
+            }
+        }
+
+        Self {
+            slots: arena.slots,
+            order: order.into_iter(),
+        }
+    }
+
+    fn take_value(&mut self, index: u32) -> T {
+        match replace(&mut self.slots[index as usize], Slot::Free(NIL)) {
+            Slot::Node(node) => node.value,
+            Slot::Free(_) => unreachable!("arena slot is taken twice"),
+        }
+    }
+}
+
+impl<T> Iterator for ArenaAvlTreeSetIntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.order.next()?;
+
+        Some(self.take_value(index))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.order.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for ArenaAvlTreeSetIntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let index = self.order.next_back()?;
+
+        Some(self.take_value(index))
+    }
+}
+
+impl<T> ExactSizeIterator for ArenaAvlTreeSetIntoIter<T> {}
+
+impl<T, C: Compare<T>> IntoIterator for ArenaAvlTreeSet<T, C> {
+    type Item = T;
+    type IntoIter = ArenaAvlTreeSetIntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        ArenaAvlTreeSetIntoIter::new(self.arena, self.root)
+    }
+}
+
+impl<'a, T, C: Compare<T>> IntoIterator for &'a ArenaAvlTreeSet<T, C> {
+    type Item = &'a T;
+    type IntoIter = ArenaAvlTreeSetIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, C: Compare<T> + Default> FromIterator<T> for ArenaAvlTreeSet<T, C> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::default();
+        set.extend(iter);
+
+        set
+    }
+}
+
+impl<T, C: Compare<T>> Extend<T> for ArenaAvlTreeSet<T, C> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<'a, T: 'a + Copy, C: Compare<T>> Extend<&'a T> for ArenaAvlTreeSet<T, C> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::values;
+    use std::collections::BTreeSet;
+    use std::ops::Bound;
+
+    #[test]
+    fn matches_btree_set() {
+        let mut set = ArenaAvlTreeSet::new();
+        let mut expected = BTreeSet::new();
+
+        for (step, value) in values(7, 4000, 500).into_iter().enumerate() {
+            if step % 3 == 0 {
+                assert_eq!(set.remove(&value), expected.remove(&value));
+            } else {
+                assert_eq!(set.insert(value), expected.insert(value));
+            }
+
+            assert!(set.is_balanced());
+            assert_eq!(set.len(), expected.len());
+        }
+
+        assert!(set.iter().eq(expected.iter()));
+        assert!(set.iter().rev().eq(expected.iter().rev()));
+        assert_eq!(set.first(), expected.iter().next());
+        assert_eq!(set.last(), expected.iter().next_back());
+
+        for (index, value) in expected.iter().enumerate() {
+            assert_eq!(set.select(index), Some(value));
+            assert_eq!(set.rank(value), index);
+        }
+
+        for probe in 0..500 {
+            assert_eq!(set.floor(&probe), expected.range(..=probe).next_back());
+            assert_eq!(set.ceiling(&probe), expected.range(probe..).next());
+            assert_eq!(set.predecessor(&probe), expected.range(..probe).next_back());
+            assert_eq!(
+                set.successor(&probe),
+                expected
+                    .range((Bound::Excluded(probe), Bound::Unbounded))
+                    .next()
+            );
+            assert!(set
+                .range(probe..probe + 50)
+                .eq(expected.range(probe..probe + 50)));
+            assert!(set.range(..=probe).rev().eq(expected.range(..=probe).rev()));
+        }
+
+        assert!(set.into_iter().eq(expected.into_iter()));
+    }
+
+    #[test]
+    fn removed_slots_are_reused() {
+        let mut set: ArenaAvlTreeSet<_> = (0..1000).collect();
+
+        for i in (0..1000).step_by(2) {
+            assert!(set.remove(&i));
+        }
+
+        assert_eq!(set.pop_first(), Some(1));
+        assert_eq!(set.pop_last(), Some(999));
+        assert!(set.is_balanced());
+
+        for i in 1000..1502 {
+            assert!(set.insert(i));
+        }
+
+        // Every new value went into a freed slot
+        assert_eq!(set.arena.slots.len(), 1000);
+        assert_eq!(set.len(), 1000);
+        assert!(set.is_balanced());
+
+        set.clear();
+        assert!(set.is_empty());
+        assert!(set.is_balanced());
+    }
+
+    #[test]
+    fn iterators_meet_in_the_middle() {
+        let set: ArenaAvlTreeSet<_> = (0..10).collect();
+
+        let mut iter = set.iter();
+        assert_eq!(iter.len(), 10);
+        assert_eq!(iter.next(), Some(&0));
+        assert_eq!(iter.next_back(), Some(&9));
+        assert!(iter.by_ref().copied().eq(1..9));
+        assert_eq!(iter.next_back(), None);
+
+        // Skipping seeks to the position on either end
+        let mut iter = set.iter();
+        assert_eq!(iter.nth(3), Some(&3));
+        assert_eq!(iter.nth_back(2), Some(&7));
+        assert_eq!(iter.len(), 3);
+        assert!(iter.clone().copied().eq(4..7));
+        assert_eq!(iter.nth(3), None);
+        assert_eq!(iter.next_back(), None);
+        assert_eq!(set.range(2..8).nth(5), Some(&7));
+        assert_eq!(set.range(2..8).nth(6), None);
+
+        let mut into_iter = set.into_iter();
+        assert_eq!(into_iter.next_back(), Some(9));
+        assert!(into_iter.eq(0..9));
+    }
+
+    #[test]
+    fn custom_comparator() {
+        let mut set = ArenaAvlTreeSet::with_comparator(|a: &i32, b: &i32| b.cmp(a));
+        set.extend(&[3, 1, 4, 1, 5, 9, 2, 6]);
+
+        assert!(set.iter().copied().eq(vec![9, 6, 5, 4, 3, 2, 1]));
+        assert!(set
+            .range((Bound::Included(5), Bound::Excluded(2)))
+            .copied()
+            .eq(vec![5, 4, 3]));
+        assert_eq!(set.take(&4), Some(4));
+        assert!(set.is_balanced());
+    }
+
+    #[test]
+    #[should_panic(expected = "range start and end are equal and excluded in ArenaAvlTreeSet")]
+    fn range_names_the_arena_set() {
+        let set: ArenaAvlTreeSet<_> = (0..10).collect();
+
+        set.range((Bound::Excluded(3), Bound::Excluded(3)));
+    }
+
+    #[test]
+    fn same_values_as_boxed_set() {
+        let values = values(3, 1000, 10_000);
+        let arena_set: ArenaAvlTreeSet<_> = values.iter().copied().collect();
+        let boxed_set: crate::AvlTreeSet<_> = values.iter().copied().collect();
+
+        assert!(arena_set.iter().eq(boxed_set.iter()));
+        assert_eq!(arena_set.height(), boxed_set.height());
+        assert_eq!(format!("{:?}", arena_set), format!("{:?}", boxed_set));
+    }
+
+    #[test]
+    fn split_off_at_every_position() {
+        for at in 0..=64 {
+            let mut set: ArenaAvlTreeSet<_> = (0..64).map(|i| i * 2).collect();
+
+            let right = set.split_off(&at);
+
+            // Either part may have moved to the new arena
+            assert!(set.is_balanced());
+            assert!(right.is_balanced());
+            assert!(set.iter().copied().eq((0..at).filter(|i| i % 2 == 0)));
+            assert!(right.iter().copied().eq((at..128).filter(|i| i % 2 == 0)));
+        }
+    }
+
+    #[test]
+    fn append_and_join_move_the_smaller_arena() {
+        let mut left: ArenaAvlTreeSet<_> = (0..100).collect();
+        let mut right: ArenaAvlTreeSet<_> = (100..110).collect();
+
+        left.append(&mut right);
+        assert!(left.is_balanced());
+        assert!(left.iter().copied().eq(0..110));
+        assert_eq!(left.arena.slots.len(), 110);
+        assert!(right.is_empty());
+
+        let mut lower: ArenaAvlTreeSet<_> = (-500..0).collect();
+        left.append(&mut lower);
+        assert!(left.is_balanced());
+        assert!(left.iter().copied().eq(-500..110));
+        assert_eq!(left.arena.slots.len(), 610);
+
+        let mut overlapping: ArenaAvlTreeSet<_> = (100..200).step_by(5).collect();
+        left.append(&mut overlapping);
+        assert!(left.is_balanced());
+        assert!(left
+            .iter()
+            .copied()
+            .eq((-500..110).chain((110..200).step_by(5))));
+
+        let set: ArenaAvlTreeSet<_> =
+            ArenaAvlTreeSet::join((0..3).collect(), 3, (4..500).collect());
+        assert!(set.is_balanced());
+        assert!(set.iter().copied().eq(0..500));
+    }
+
+    #[test]
+    #[should_panic(expected = "joined sets must be ordered around the pivot in ArenaAvlTreeSet")]
+    fn join_unordered() {
+        ArenaAvlTreeSet::join((5..6).collect(), 3, ArenaAvlTreeSet::new());
+    }
+
+    #[test]
+    fn renders_and_compares_like_boxed_set() {
+        let arena_set: ArenaAvlTreeSet<_> = vec![2, 1, 3, 4].into_iter().collect();
+        let boxed_set: crate::AvlTreeSet<_> = vec![2, 1, 3, 4].into_iter().collect();
+
+        assert_eq!(
+            arena_set.to_dot(),
+            boxed_set.to_dot().replace("AvlTreeSet", "ArenaAvlTreeSet")
+        );
+        assert_eq!(arena_set.to_pretty_string(), boxed_set.to_pretty_string());
+
+        // Same values in another shape
+        let other: ArenaAvlTreeSet<_> = (1..=4).rev().collect();
+        assert!(arena_set.structural_eq(&arena_set.clone()));
+        assert!(!arena_set.structural_eq(&other));
+        assert_eq!(arena_set, other);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn validate_names_the_arena_set() {
+        let mut broken: ArenaAvlTreeSet<_> = (1..=4).rev().collect();
+        assert_eq!(broken.validate(), Ok(()));
+
+        let root = broken.root as usize;
+        if let Slot::Node(root_node) = &mut broken.arena.slots[root] {
+            root_node.value = 10;
+        }
+
+        assert_eq!(
+            broken.validate().unwrap_err().to_string(),
+            "ArenaAvlTreeSet node at root.right is out of order with its ancestors"
+        );
+    }
+}
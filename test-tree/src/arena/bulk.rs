@@ -0,0 +1,70 @@
+use super::ArenaAvlTreeSet;
+use crate::compare::{Compare, Natural};
+use crate::set::{check_sorted, SortedInput, UnsortedError};
+
+// Same checks as the boxed set, with the tree built bottom up into one arena
+
+impl<T: Ord> ArenaAvlTreeSet<T> {
+    pub fn from_sorted_iter<I>(iter: I, input: SortedInput) -> Result<Self, UnsortedError>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        Self::from_sorted_vec(iter.into_iter().collect(), input)
+    }
+
+    pub fn from_sorted_vec(values: Vec<T>, input: SortedInput) -> Result<Self, UnsortedError> {
+        Self::from_sorted_vec_with_comparator(values, Natural, input)
+    }
+}
+
+impl<T, C: Compare<T>> ArenaAvlTreeSet<T, C> {
+    // The values must be sorted by the given comparator
+    pub fn from_sorted_vec_with_comparator(
+        mut values: Vec<T>,
+        cmp: C,
+        input: SortedInput,
+    ) -> Result<Self, UnsortedError> {
+        check_sorted(&mut values, &cmp, input, "ArenaAvlTreeSet")?;
+
+        let len = values.len();
+        let mut set = Self::with_comparator(cmp);
+        set.arena.slots.reserve_exact(len);
+        set.root = set.arena.build_sorted(&mut values.into_iter(), len);
+
+        Ok(set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_minimal_height_trees() {
+        for len in 0..300usize {
+            let set = ArenaAvlTreeSet::from_sorted_iter(0..len, SortedInput::Validate).unwrap();
+
+            assert!(set.is_balanced());
+            assert!(set.iter().copied().eq(0..len));
+
+            let min_height = (usize::BITS - len.leading_zeros()) as usize;
+            assert_eq!(set.height(), min_height);
+        }
+    }
+
+    #[test]
+    fn rejects_unsorted_input() {
+        let error = ArenaAvlTreeSet::from_sorted_vec(vec![1, 2, 2, 3], SortedInput::Validate);
+        let error = error.unwrap_err();
+
+        assert_eq!(error.index(), 2);
+        assert_eq!(
+            error.to_string(),
+            "value at index 2 is out of order in sorted input for ArenaAvlTreeSet"
+        );
+
+        let set =
+            ArenaAvlTreeSet::from_sorted_vec(vec![1, 1, 2, 3, 3], SortedInput::Dedup).unwrap();
+        assert!(set.iter().copied().eq(1..=3));
+    }
+}
@@ -0,0 +1,440 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt::{self, Debug};
+
+use super::{Arena, ArenaAvlTreeSet, NIL};
+use crate::compare::{Compare, Natural};
+
+// Cursors move like the ones of the boxed set, with the ghost position past
+// the last value. Indices into the arena stay valid as long as the tree does
+// not change, so both cursors keep the path down to the current value as
+// indices. After an edit the mutable cursor seeks its position from the root.
+
+// Position of a cursor and the nodes from the root down to it, empty at the
+// ghost position
+#[derive(Debug, Clone)]
+struct Path {
+    nodes: Vec<u32>,
+    index: usize,
+}
+
+impl Path {
+    fn new<T>(arena: &Arena<T>, root: u32, index: usize) -> Self {
+        let mut path = Self {
+            nodes: Vec::new(),
+            index,
+        };
+        path.seek(arena, root);
+
+        path
+    }
+
+    fn seek<T>(&mut self, arena: &Arena<T>, root: u32) {
+        self.nodes.clear();
+
+        let mut position = self.index;
+        let mut index = root;
+
+        while index != NIL {
+            let current_node = arena.node(index);
+            let left_size = arena.size_of(current_node.left) as usize;
+            self.nodes.push(index);
+
+            match position.cmp(&left_size) {
+                Ordering::Less => index = current_node.left,
+                Ordering::Equal => return,
+                Ordering::Greater => {
+                    position -= left_size + 1;
+                    index = current_node.right;
+                }
+            }
+        }
+
+        // Past the last value
+        self.nodes.clear();
+    }
+
+    fn push_left<T>(&mut self, arena: &Arena<T>, mut index: u32) {
+        while index != NIL {
+            self.nodes.push(index);
+            index = arena.node(index).left;
+        }
+    }
+
+    fn push_right<T>(&mut self, arena: &Arena<T>, mut index: u32) {
+        while index != NIL {
+            self.nodes.push(index);
+            index = arena.node(index).right;
+        }
+    }
+
+    fn current<'a, T>(&self, arena: &'a Arena<T>) -> Option<&'a T> {
+        self.nodes.last().map(|&index| &arena.node(index).value)
+    }
+
+    fn move_next<T>(&mut self, arena: &Arena<T>, root: u32) {
+        let current_index = match self.nodes.last() {
+            Some(&current_index) => current_index,
+            None => {
+                self.index = 0;
+                self.push_left(arena, root);
+
+                return;
+            }
+        };
+
+        self.index += 1;
+
+        let right = arena.node(current_index).right;
+
+        if right != NIL {
+            self.push_left(arena, right);
+            return;
+        }
+
+        // Climb until coming up from a left subtree, whose parent is next
+        while let Some(child) = self.nodes.pop() {
+            if self
+                .nodes
+                .last()
+                .is_some_and(|&parent| arena.node(parent).left == child)
+            {
+                return;
+            }
+        }
+    }
+
+    fn move_prev<T>(&mut self, arena: &Arena<T>, root: u32) {
+        let len = arena.size_of(root) as usize;
+        let current_index = match self.nodes.last() {
+            Some(&current_index) => current_index,
+            None => {
+                self.index = len.saturating_sub(1);
+                self.push_right(arena, root);
+
+                return;
+            }
+        };
+
+        let left = arena.node(current_index).left;
+
+        if left != NIL {
+            self.index -= 1;
+            self.push_right(arena, left);
+            return;
+        }
+
+        while let Some(child) = self.nodes.pop() {
+            if self
+                .nodes
+                .last()
+                .is_some_and(|&parent| arena.node(parent).right == child)
+            {
+                self.index -= 1;
+                return;
+            }
+        }
+
+        // Before the first value comes the ghost
+        self.index = len;
+    }
+
+    fn peek_next<'a, T>(&self, arena: &'a Arena<T>, root: u32) -> Option<&'a T> {
+        let current_index = match self.nodes.last() {
+            Some(&current_index) => current_index,
+            None => return arena.first(root),
+        };
+
+        let right = arena.node(current_index).right;
+
+        if right != NIL {
+            return arena.first(right);
+        }
+
+        self.nodes
+            .windows(2)
+            .rev()
+            .find(|pair| arena.node(pair[0]).left == pair[1])
+            .map(|pair| &arena.node(pair[0]).value)
+    }
+
+    fn peek_prev<'a, T>(&self, arena: &'a Arena<T>, root: u32) -> Option<&'a T> {
+        let current_index = match self.nodes.last() {
+            Some(&current_index) => current_index,
+            None => return arena.last(root),
+        };
+
+        let left = arena.node(current_index).left;
+
+        if left != NIL {
+            return arena.last(left);
+        }
+
+        self.nodes
+            .windows(2)
+            .rev()
+            .find(|pair| arena.node(pair[0]).right == pair[1])
+            .map(|pair| &arena.node(pair[0]).value)
+    }
+}
+
+pub struct ArenaCursor<'a, T, C = Natural> {
+    set: &'a ArenaAvlTreeSet<T, C>,
+    path: Path,
+}
+
+impl<'a, T, C> Clone for ArenaCursor<'a, T, C> {
+    fn clone(&self) -> Self {
+        Self {
+            set: self.set,
+            path: self.path.clone(),
+        }
+    }
+}
+
+impl<'a, T: Debug, C> Debug for ArenaCursor<'a, T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArenaCursor")
+            .field("index", &self.path.index)
+            .field("current", &self.path.current(&self.set.arena))
+            .finish()
+    }
+}
+
+impl<'a, T, C: Compare<T>> ArenaCursor<'a, T, C> {
+    // Position of the current value, None at the ghost position
+    pub fn index(&self) -> Option<usize> {
+        Some(self.path.index).filter(|index| *index < self.set.len())
+    }
+
+    pub fn current(&self) -> Option<&'a T> {
+        self.path.current(&self.set.arena)
+    }
+
+    pub fn move_next(&mut self) {
+        self.path.move_next(&self.set.arena, self.set.root);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.path.move_prev(&self.set.arena, self.set.root);
+    }
+
+    pub fn peek_next(&self) -> Option<&'a T> {
+        self.path.peek_next(&self.set.arena, self.set.root)
+    }
+
+    pub fn peek_prev(&self) -> Option<&'a T> {
+        self.path.peek_prev(&self.set.arena, self.set.root)
+    }
+}
+
+pub struct ArenaCursorMut<'a, T, C = Natural> {
+    set: &'a mut ArenaAvlTreeSet<T, C>,
+    path: Path,
+}
+
+impl<'a, T: Debug, C> Debug for ArenaCursorMut<'a, T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArenaCursorMut")
+            .field("index", &self.path.index)
+            .field("current", &self.path.current(&self.set.arena))
+            .finish()
+    }
+}
+
+impl<'a, T, C: Compare<T>> ArenaCursorMut<'a, T, C> {
+    pub fn index(&self) -> Option<usize> {
+        Some(self.path.index).filter(|index| *index < self.set.len())
+    }
+
+    pub fn current(&self) -> Option<&T> {
+        self.path.current(&self.set.arena)
+    }
+
+    pub fn move_next(&mut self) {
+        self.path.move_next(&self.set.arena, self.set.root);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.path.move_prev(&self.set.arena, self.set.root);
+    }
+
+    pub fn peek_next(&self) -> Option<&T> {
+        self.path.peek_next(&self.set.arena, self.set.root)
+    }
+
+    pub fn peek_prev(&self) -> Option<&T> {
+        self.path.peek_prev(&self.set.arena, self.set.root)
+    }
+
+    pub fn as_cursor(&self) -> ArenaCursor<'_, T, C> {
+        ArenaCursor {
+            set: self.set,
+            path: self.path.clone(),
+        }
+    }
+
+    // Inserts the value right before the current one, or as the last value at
+    // the ghost position. The cursor keeps pointing at the same value.
+    pub fn insert_before(&mut self, value: T) {
+        assert_ordered(&self.set.cmp, self.peek_prev(), &value, self.current());
+
+        self.set.insert(value);
+        self.path.index += 1;
+        self.path.seek(&self.set.arena, self.set.root);
+    }
+
+    // Inserts the value right after the current one, or as the first value at
+    // the ghost position. The cursor keeps pointing at the same value.
+    pub fn insert_after(&mut self, value: T) {
+        assert_ordered(&self.set.cmp, self.current(), &value, self.peek_next());
+
+        if self.path.nodes.is_empty() {
+            self.path.index += 1;
+        }
+
+        self.set.insert(value);
+        self.path.seek(&self.set.arena, self.set.root);
+    }
+
+    // Removes the current value and moves on to the next one
+    pub fn remove_current(&mut self) -> Option<T> {
+        let (root, value) = self.set.arena.take_at(self.set.root, self.path.index);
+        self.set.root = root;
+
+        if value.is_some() {
+            self.path.seek(&self.set.arena, self.set.root);
+        }
+
+        value
+    }
+}
+
+fn assert_ordered<T, C: Compare<T>>(cmp: &C, lower: Option<&T>, value: &T, upper: Option<&T>) {
+    assert!(
+        lower.is_none_or(|lower| cmp.compare(lower, value) == Ordering::Less)
+            && upper.is_none_or(|upper| cmp.compare(value, upper) == Ordering::Less),
+        "inserted value must keep the ArenaAvlTreeSet ordered"
+    );
+}
+
+impl<T, C: Compare<T>> ArenaAvlTreeSet<T, C> {
+    pub fn cursor_front(&self) -> ArenaCursor<'_, T, C> {
+        ArenaCursor {
+            set: self,
+            path: Path::new(&self.arena, self.root, 0),
+        }
+    }
+
+    // Cursor at the first value greater than or equal to the given one
+    pub fn lower_bound_cursor<Q>(&self, value: &Q) -> ArenaCursor<'_, T, C>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        ArenaCursor {
+            set: self,
+            path: Path::new(&self.arena, self.root, self.rank(value)),
+        }
+    }
+
+    pub fn cursor_front_mut(&mut self) -> ArenaCursorMut<'_, T, C> {
+        let path = Path::new(&self.arena, self.root, 0);
+
+        ArenaCursorMut { set: self, path }
+    }
+
+    pub fn lower_bound_cursor_mut<Q>(&mut self, value: &Q) -> ArenaCursorMut<'_, T, C>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let path = Path::new(&self.arena, self.root, self.rank(value));
+
+        ArenaCursorMut { set: self, path }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walk_matches_iter() {
+        let mut set: ArenaAvlTreeSet<_> = (0..300).map(|i| i * 7 % 300).collect();
+        let values: Vec<i32> = set.iter().copied().collect();
+
+        let mut cursor = set.cursor_front();
+        for (index, value) in values.iter().enumerate() {
+            assert_eq!(cursor.index(), Some(index));
+            assert_eq!(cursor.current(), Some(value));
+            assert_eq!(cursor.peek_next(), values.get(index + 1));
+            cursor.move_next();
+        }
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.peek_next(), Some(&0));
+
+        for (index, value) in values.iter().enumerate().rev() {
+            cursor.move_prev();
+            assert_eq!(cursor.index(), Some(index));
+            assert_eq!(cursor.current(), Some(value));
+            assert_eq!(cursor.peek_prev(), index.checked_sub(1).map(|i| &values[i]));
+        }
+
+        // The mutable cursor walks the same way, also on after an edit
+        let mut cursor = set.lower_bound_cursor_mut(&100);
+        for value in 100..150 {
+            assert_eq!(cursor.current(), Some(&value));
+            assert_eq!(cursor.peek_prev(), Some(&(value - 1)));
+            cursor.move_next();
+        }
+        assert_eq!(cursor.remove_current(), Some(150));
+        assert_eq!(cursor.current(), Some(&151));
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&149));
+        cursor.move_prev();
+        assert_eq!(cursor.as_cursor().peek_next(), Some(&149));
+        drop(cursor);
+
+        assert!(set.is_balanced());
+        assert!(!set.contains(&150));
+    }
+
+    #[test]
+    fn edit_in_place() {
+        let mut set: ArenaAvlTreeSet<_> = (0..10).map(|i| i * 10).collect();
+
+        let mut cursor = set.lower_bound_cursor_mut(&40);
+        cursor.insert_before(35);
+        cursor.insert_after(45);
+        assert_eq!(cursor.current(), Some(&40));
+        assert_eq!(cursor.peek_prev(), Some(&35));
+        assert_eq!(cursor.peek_next(), Some(&45));
+        assert_eq!(cursor.remove_current(), Some(40));
+        assert_eq!(cursor.current(), Some(&45));
+
+        // Edits at the ghost position append at either end
+        let mut cursor = set.lower_bound_cursor_mut(&1000);
+        cursor.insert_before(100);
+        cursor.insert_after(-10);
+        assert_eq!(cursor.current(), None);
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&-10));
+
+        assert!(set.is_balanced());
+        assert!(set
+            .iter()
+            .copied()
+            .eq(vec![-10, 0, 10, 20, 30, 35, 45, 50, 60, 70, 80, 90, 100]));
+    }
+
+    #[test]
+    #[should_panic(expected = "inserted value must keep the ArenaAvlTreeSet ordered")]
+    fn insert_out_of_order() {
+        let mut set: ArenaAvlTreeSet<_> = (0..10).collect();
+
+        set.lower_bound_cursor_mut(&5).insert_before(7);
+    }
+}
@@ -0,0 +1,197 @@
+use std::iter::FusedIterator;
+use std::mem::replace;
+
+use super::{Arena, ArenaAvlTreeSet, ArenaAvlTreeSetIntoIter, ArenaAvlTreeSetIter, NIL};
+use crate::compare::Compare;
+
+// Removes and yields the values the predicate holds for, in sorted order, and
+// switches from removing in place to rebuilding like `ExtractIf` does. The
+// rebuilt tree goes into a new arena without any free slots.
+pub struct ArenaExtractIf<'a, T, C, F>
+where
+    F: FnMut(&T) -> bool,
+{
+    set: &'a mut ArenaAvlTreeSet<T, C>,
+    pred: F,
+    // Position of the next value to check while removing in place
+    index: usize,
+    removed: usize,
+    // Kept values so far and the values still to check, once rebuilding
+    rebuild: Option<(Vec<T>, ArenaAvlTreeSetIntoIter<T>)>,
+}
+
+impl<'a, T, C, F> ArenaExtractIf<'a, T, C, F>
+where
+    F: FnMut(&T) -> bool,
+{
+    // Every value before the current position is kept
+    fn start_rebuild(&mut self) {
+        let arena = replace(&mut self.set.arena, Arena::new());
+        let root = replace(&mut self.set.root, NIL);
+        let mut values = ArenaAvlTreeSetIntoIter::new(arena, root);
+        let mut kept = Vec::with_capacity(values.len());
+        kept.extend(values.by_ref().take(self.index));
+
+        self.rebuild = Some((kept, values));
+    }
+}
+
+impl<'a, T, C, F> Iterator for ArenaExtractIf<'a, T, C, F>
+where
+    F: FnMut(&T) -> bool,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((kept, values)) = &mut self.rebuild {
+            for value in values {
+                if (self.pred)(&value) {
+                    return Some(value);
+                }
+
+                kept.push(value);
+            }
+
+            return None;
+        }
+
+        let set = &mut *self.set;
+        let len = set.arena.size_of(set.root) as usize;
+        let offset = ArenaAvlTreeSetIter::new(&set.arena.slots, set.root, self.index, len)
+            .position(&mut self.pred);
+
+        let offset = match offset {
+            Some(offset) => offset,
+            None => {
+                self.index = len;
+
+                return None;
+            }
+        };
+
+        self.index += offset;
+        self.removed += 1;
+
+        let (root, value) = set.arena.take_at(set.root, self.index);
+        set.root = root;
+
+        if self.removed * set.arena.height_of(root) as usize > set.arena.size_of(root) as usize {
+            self.start_rebuild();
+        }
+
+        value
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.rebuild {
+            Some((_, values)) => (0, Some(values.len())),
+            None => (
+                0,
+                Some(self.set.arena.size_of(self.set.root) as usize - self.index),
+            ),
+        }
+    }
+}
+
+impl<'a, T, C, F> FusedIterator for ArenaExtractIf<'a, T, C, F> where F: FnMut(&T) -> bool {}
+
+impl<'a, T, C, F> Drop for ArenaExtractIf<'a, T, C, F>
+where
+    F: FnMut(&T) -> bool,
+{
+    fn drop(&mut self) {
+        if let Some((mut kept, values)) = self.rebuild.take() {
+            kept.extend(values);
+
+            let len = kept.len();
+            self.set.arena.slots.reserve_exact(len);
+            self.set.root = self.set.arena.build_sorted(&mut kept.into_iter(), len);
+        }
+    }
+}
+
+impl<T, C: Compare<T>> ArenaAvlTreeSet<T, C> {
+    pub fn extract_if<F>(&mut self, pred: F) -> ArenaExtractIf<'_, T, C, F>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        ArenaExtractIf {
+            set: self,
+            pred,
+            index: 0,
+            removed: 0,
+            rebuild: None,
+        }
+    }
+
+    // Keeps the values the predicate holds for, checking each of them once in
+    // sorted order
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.extract_if(|value| !f(value)).for_each(drop);
+    }
+
+    // Moves all values out in sorted order and leaves the set empty, even when
+    // the iterator is dropped early
+    pub fn drain(&mut self) -> ArenaAvlTreeSetIntoIter<T> {
+        let arena = replace(&mut self.arena, Arena::new());
+        let root = replace(&mut self.root, NIL);
+
+        ArenaAvlTreeSetIntoIter::new(arena, root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retain_keeps_matching_values() {
+        // Few removals stay in place, many removals rebuild the tree
+        for &step in [1000, 7, 1].iter() {
+            let mut set: ArenaAvlTreeSet<u32> = (0..10_000).collect();
+
+            set.retain(|&value| value % step != 0);
+
+            assert!(set.is_balanced());
+            assert!(set
+                .iter()
+                .copied()
+                .eq((0..10_000).filter(|value| value % step != 0)));
+        }
+    }
+
+    #[test]
+    fn dropped_extract_if_keeps_the_rest() {
+        for &taken in [3, 400].iter() {
+            let mut set: ArenaAvlTreeSet<u32> = (0..1000).collect();
+
+            let removed: Vec<_> = set.extract_if(|value| value % 2 == 0).take(taken).collect();
+
+            assert!(removed.into_iter().eq((0..).step_by(2).take(taken)));
+            assert!(set.is_balanced());
+            assert!(set
+                .iter()
+                .copied()
+                .eq((0..1000).filter(|&value| value % 2 == 1 || value >= taken as u32 * 2)));
+        }
+    }
+
+    #[test]
+    fn drain_empties_the_set() {
+        let mut set: ArenaAvlTreeSet<u32> = (0..100).rev().collect();
+
+        let mut drained = set.drain();
+        assert_eq!(drained.len(), 100);
+        assert_eq!(drained.next(), Some(0));
+        drop(drained);
+
+        assert!(set.is_empty());
+        assert!(set.is_balanced());
+
+        set.insert(1);
+        assert!(set.drain().eq(vec![1]));
+    }
+}
@@ -0,0 +1,188 @@
+use std::ops::{BitAnd, BitOr, BitXor, Sub};
+
+use super::{ArenaAvlTreeSet, ArenaAvlTreeSetIter};
+use crate::compare::Compare;
+use crate::set::{Difference, Intersection, SymmetricDifference, Union};
+
+// Lazy forms are the ones of the boxed set walking the arena iterators
+
+impl<'a, T: 'a, C: Compare<T>> ArenaAvlTreeSet<T, C> {
+    pub fn union(&'a self, other: &'a Self) -> Union<'a, T, C, ArenaAvlTreeSetIter<'a, T>> {
+        Union::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    pub fn intersection(
+        &'a self,
+        other: &'a Self,
+    ) -> Intersection<'a, T, C, ArenaAvlTreeSetIter<'a, T>> {
+        Intersection::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    pub fn difference(
+        &'a self,
+        other: &'a Self,
+    ) -> Difference<'a, T, C, ArenaAvlTreeSetIter<'a, T>> {
+        Difference::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    pub fn symmetric_difference(
+        &'a self,
+        other: &'a Self,
+    ) -> SymmetricDifference<'a, T, C, ArenaAvlTreeSetIter<'a, T>> {
+        SymmetricDifference::new(self.iter(), other.iter(), &self.cmp)
+    }
+}
+
+// Consuming forms move both trees into one arena and rebuild them with split
+// and join there
+impl<T, C: Compare<T>> ArenaAvlTreeSet<T, C> {
+    pub fn into_union(mut self, mut other: Self) -> Self {
+        let (tree, other_tree) = self.merge_arenas(&mut other);
+        let cmp = &self.cmp;
+        self.root = self
+            .arena
+            .union(tree, other_tree, &mut |a: &T, b: &T| cmp.compare(a, b));
+
+        self
+    }
+
+    pub fn into_intersection(mut self, mut other: Self) -> Self {
+        let (tree, other_tree) = self.merge_arenas(&mut other);
+        let cmp = &self.cmp;
+        self.root = self
+            .arena
+            .intersection(tree, other_tree, &mut |a: &T, b: &T| cmp.compare(a, b));
+
+        self
+    }
+
+    pub fn into_difference(mut self, mut other: Self) -> Self {
+        let (tree, other_tree) = self.merge_arenas(&mut other);
+        let cmp = &self.cmp;
+        self.root = self
+            .arena
+            .difference(tree, other_tree, &mut |a: &T, b: &T| cmp.compare(a, b));
+
+        self
+    }
+
+    pub fn into_symmetric_difference(mut self, mut other: Self) -> Self {
+        let (tree, other_tree) = self.merge_arenas(&mut other);
+        let cmp = &self.cmp;
+        self.root = self
+            .arena
+            .symmetric_difference(tree, other_tree, &mut |a: &T, b: &T| cmp.compare(a, b));
+
+        self
+    }
+}
+
+impl<T: Clone, C: Compare<T> + Clone> BitOr<&ArenaAvlTreeSet<T, C>> for &ArenaAvlTreeSet<T, C> {
+    type Output = ArenaAvlTreeSet<T, C>;
+
+    fn bitor(self, rhs: &ArenaAvlTreeSet<T, C>) -> ArenaAvlTreeSet<T, C> {
+        self.clone().into_union(rhs.clone())
+    }
+}
+
+impl<T: Clone, C: Compare<T> + Clone> BitAnd<&ArenaAvlTreeSet<T, C>> for &ArenaAvlTreeSet<T, C> {
+    type Output = ArenaAvlTreeSet<T, C>;
+
+    fn bitand(self, rhs: &ArenaAvlTreeSet<T, C>) -> ArenaAvlTreeSet<T, C> {
+        self.clone().into_intersection(rhs.clone())
+    }
+}
+
+impl<T: Clone, C: Compare<T> + Clone> Sub<&ArenaAvlTreeSet<T, C>> for &ArenaAvlTreeSet<T, C> {
+    type Output = ArenaAvlTreeSet<T, C>;
+
+    fn sub(self, rhs: &ArenaAvlTreeSet<T, C>) -> ArenaAvlTreeSet<T, C> {
+        self.clone().into_difference(rhs.clone())
+    }
+}
+
+impl<T: Clone, C: Compare<T> + Clone> BitXor<&ArenaAvlTreeSet<T, C>> for &ArenaAvlTreeSet<T, C> {
+    type Output = ArenaAvlTreeSet<T, C>;
+
+    fn bitxor(self, rhs: &ArenaAvlTreeSet<T, C>) -> ArenaAvlTreeSet<T, C> {
+        self.clone().into_symmetric_difference(rhs.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::values;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn lazy_forms() {
+        let left: ArenaAvlTreeSet<u32> = vec![1, 3, 5, 7, 9].into_iter().collect();
+        let right: ArenaAvlTreeSet<u32> = vec![3, 4, 5, 6].into_iter().collect();
+
+        assert!(left.union(&right).copied().eq(vec![1, 3, 4, 5, 6, 7, 9]));
+        assert!(left.intersection(&right).copied().eq(vec![3, 5]));
+        assert!(left.difference(&right).copied().eq(vec![1, 7, 9]));
+        assert!(left
+            .symmetric_difference(&right)
+            .copied()
+            .eq(vec![1, 4, 6, 7, 9]));
+    }
+
+    #[test]
+    fn consuming_forms_match_btree_set() {
+        // Either arena can be the larger one, and removing values from the
+        // left set first leaves free slots behind
+        let sizes = [(0, 50), (50, 0), (1, 300), (300, 1), (40, 400), (500, 500)];
+
+        for (seed, (left_size, right_size)) in sizes.iter().enumerate() {
+            let left_values = values(seed as u64, *left_size, 1000);
+            let right_values = values(seed as u64 + 100, *right_size, 1000);
+
+            let mut left: ArenaAvlTreeSet<u32> = left_values.iter().copied().collect();
+            let right: ArenaAvlTreeSet<u32> = right_values.iter().copied().collect();
+            left.retain(|value| value % 7 != 0);
+
+            let left_expected: BTreeSet<_> = left.iter().copied().collect();
+            let right_expected: BTreeSet<_> = right_values.iter().copied().collect();
+
+            let checks = vec![
+                (
+                    left.clone().into_union(right.clone()),
+                    &left_expected | &right_expected,
+                ),
+                (
+                    left.clone().into_intersection(right.clone()),
+                    &left_expected & &right_expected,
+                ),
+                (
+                    left.clone().into_difference(right.clone()),
+                    &left_expected - &right_expected,
+                ),
+                (
+                    left.clone().into_symmetric_difference(right.clone()),
+                    &left_expected ^ &right_expected,
+                ),
+            ];
+
+            for (result, expected) in checks {
+                assert!(result.is_balanced());
+                assert_eq!(result.len(), expected.len());
+                assert!(result.iter().eq(expected.iter()));
+            }
+        }
+    }
+
+    #[test]
+    fn operators() {
+        let left: ArenaAvlTreeSet<u32> = (1..=3).collect();
+        let right: ArenaAvlTreeSet<u32> = (2..=4).collect();
+
+        assert!((&left | &right).iter().copied().eq(vec![1, 2, 3, 4]));
+        assert!((&left & &right).iter().copied().eq(vec![2, 3]));
+        assert!((&left - &right).iter().copied().eq(vec![1]));
+        assert!((&left ^ &right).iter().copied().eq(vec![1, 4]));
+        assert_eq!(left.len(), 3);
+        assert_eq!(right.len(), 3);
+    }
+}
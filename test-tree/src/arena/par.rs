@@ -0,0 +1,201 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Debug};
+use std::mem::replace;
+
+use rayon::iter::plumbing::{bridge, Consumer, Producer, ProducerCallback, UnindexedConsumer};
+use rayon::iter::{
+    FromParallelIterator, IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator,
+    ParallelIterator,
+};
+use rayon::slice::ParallelSliceMut;
+
+use super::{Arena, ArenaAvlTreeSet, ArenaAvlTreeSetIntoIter, ArenaAvlTreeSetIter, Slot, NIL};
+use crate::compare::Compare;
+
+// Parallel iterator over the values of an arena set, split by positions like
+// `AvlTreeSetParIter`
+pub struct ArenaAvlTreeSetParIter<'a, T> {
+    slots: &'a [Slot<T>],
+    root: u32,
+    len: usize,
+}
+
+impl<'a, T: Debug> Debug for ArenaAvlTreeSetParIter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        ArenaAvlTreeSetIter::new(self.slots, self.root, 0, self.len).fmt(f)
+    }
+}
+
+impl<'a, T: Sync + 'a> ParallelIterator for ArenaAvlTreeSetParIter<'a, T> {
+    type Item = &'a T;
+
+    fn drive_unindexed<S>(self, consumer: S) -> S::Result
+    where
+        S: UnindexedConsumer<Self::Item>,
+    {
+        bridge(self, consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'a, T: Sync + 'a> IndexedParallelIterator for ArenaAvlTreeSetParIter<'a, T> {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn drive<S: Consumer<Self::Item>>(self, consumer: S) -> S::Result {
+        bridge(self, consumer)
+    }
+
+    fn with_producer<CB: ProducerCallback<Self::Item>>(self, callback: CB) -> CB::Output {
+        callback.callback(SetProducer {
+            slots: self.slots,
+            root: self.root,
+            front: 0,
+            back: self.len,
+        })
+    }
+}
+
+// The values at the positions front..back
+struct SetProducer<'a, T> {
+    slots: &'a [Slot<T>],
+    root: u32,
+    front: usize,
+    back: usize,
+}
+
+impl<'a, T: Sync + 'a> Producer for SetProducer<'a, T> {
+    type Item = &'a T;
+    type IntoIter = ArenaAvlTreeSetIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        ArenaAvlTreeSetIter::new(self.slots, self.root, self.front, self.back)
+    }
+
+    fn split_at(self, index: usize) -> (Self, Self) {
+        let middle = self.front + index;
+
+        (
+            SetProducer {
+                slots: self.slots,
+                root: self.root,
+                front: self.front,
+                back: middle,
+            },
+            SetProducer {
+                slots: self.slots,
+                root: self.root,
+                front: middle,
+                back: self.back,
+            },
+        )
+    }
+}
+
+impl<'a, T: Sync + 'a, C: Compare<T>> IntoParallelIterator for &'a ArenaAvlTreeSet<T, C> {
+    type Item = &'a T;
+    type Iter = ArenaAvlTreeSetParIter<'a, T>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        ArenaAvlTreeSetParIter {
+            slots: &self.arena.slots,
+            root: self.root,
+            len: self.len(),
+        }
+    }
+}
+
+// Sorts and dedups the values like the boxed set does and builds the tree
+// into an arena of exactly their number
+impl<T: Send, C: Compare<T> + Default + Sync> FromParallelIterator<T> for ArenaAvlTreeSet<T, C> {
+    fn from_par_iter<I>(par_iter: I) -> Self
+    where
+        I: IntoParallelIterator<Item = T>,
+    {
+        let mut values: Vec<T> = par_iter.into_par_iter().collect();
+        let mut set = Self::default();
+        let cmp = &set.cmp;
+
+        values.par_sort_by(|a, b| cmp.compare(a, b));
+        values.dedup_by(|value, kept| cmp.compare(kept, value) == Ordering::Equal);
+
+        let len = values.len();
+        set.arena.slots.reserve_exact(len);
+        set.root = set.arena.build_sorted(&mut values.into_iter(), len);
+
+        set
+    }
+}
+
+impl<T: Send + Sync, C: Compare<T>> ArenaAvlTreeSet<T, C> {
+    // Keeps the values the predicate holds for. The predicate runs in parallel,
+    // then the tree gets rebuilt out of the kept values in O(n).
+    pub fn par_retain<F>(&mut self, f: F)
+    where
+        F: Fn(&T) -> bool + Sync,
+    {
+        let keep: Vec<bool> = self.par_iter().map(&f).collect();
+        let len = keep.iter().filter(|&&keep| keep).count();
+
+        if len == keep.len() {
+            return;
+        }
+
+        let arena = replace(&mut self.arena, Arena::new());
+        let root = replace(&mut self.root, NIL);
+        let mut kept = ArenaAvlTreeSetIntoIter::new(arena, root)
+            .zip(keep)
+            .filter_map(|(value, keep)| if keep { Some(value) } else { None });
+        self.arena.slots.reserve_exact(len);
+        self.root = self.arena.build_sorted(&mut kept, len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn par_iter_matches_iter() {
+        let mut set: ArenaAvlTreeSet<u64> = (0..100_000).map(|i| i * 3).collect();
+        set.retain(|value| value % 2 == 0);
+
+        assert_eq!(set.par_iter().len(), set.len());
+        assert_eq!(set.par_iter().sum::<u64>(), set.iter().sum());
+        assert_eq!(
+            set.par_iter().with_min_len(7).collect::<Vec<_>>(),
+            set.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn from_par_iter_sorts_and_dedups() {
+        let set: ArenaAvlTreeSet<_> = (0..50_000u32)
+            .into_par_iter()
+            .map(|i| i * 7 % 10_000)
+            .collect();
+
+        assert!(set.is_balanced());
+        assert!(set.iter().copied().eq(0..10_000));
+    }
+
+    #[test]
+    fn par_retain_rebuilds_balanced() {
+        let mut set: ArenaAvlTreeSet<u32> = (0..100_000).collect();
+
+        set.par_retain(|value| value % 3 == 0);
+        assert!(set.is_balanced());
+        assert!(set
+            .iter()
+            .copied()
+            .eq((0..100_000).filter(|value| value % 3 == 0)));
+
+        set.par_retain(|_| false);
+        assert!(set.is_empty());
+        assert!(set.is_balanced());
+    }
+}
@@ -0,0 +1,306 @@
+use std::borrow::Borrow;
+use std::fmt::{self, Debug};
+use std::iter::FromIterator;
+use std::mem::replace;
+use std::ops::{Bound, RangeBounds};
+
+use crate::compare::{assert_range, is_after, is_before, Natural};
+use crate::map::{cmp_keys, AvlTreeMapIter};
+use crate::node::{find, fold, insert, modify, size, take, AvlTree};
+use crate::summary::Summary;
+
+// Map whose nodes cache the summary of the entries in their subtree, which
+// makes the summary of any key range available in O(log n), like a segment
+// tree that is ordered by its keys rather than by position
+#[derive(Clone)]
+pub struct AugmentedAvlTreeMap<K: Ord, V, S: Summary<(K, V)>> {
+    root: AvlTree<(K, V), S>,
+}
+
+impl<K: Ord + Debug, V: Debug, S: Summary<(K, V)>> Debug for AugmentedAvlTreeMap<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Ord, V, S: Summary<(K, V)>> Default for AugmentedAvlTreeMap<K, V, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V, S: Summary<(K, V)>> AugmentedAvlTreeMap<K, V, S> {
+    pub fn new() -> Self {
+        Self { root: None }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match insert(&mut self.root, (key, value), &mut cmp_keys) {
+            Ok(_) => None,
+
+            // The summaries above a replaced value have to be refreshed too
+            Err((key, value)) => modify(
+                &mut self.root,
+                &mut |(current_key, _): &(K, V)| current_key.cmp(&key),
+                |(_, current_value)| replace(current_value, value),
+            ),
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        find(&self.root, |(current_key, _)| current_key.borrow().cmp(key)).map(|(_, value)| value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    // Changes the value of the key in place, there is no `get_mut` because the
+    // cached summaries could not be kept up to date behind a plain reference
+    pub fn modify<Q, F, R>(&mut self, key: &Q, f: F) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        F: FnOnce(&mut V) -> R,
+    {
+        modify(
+            &mut self.root,
+            &mut |(current_key, _): &(K, V)| current_key.borrow().cmp(key),
+            |(_, value)| f(value),
+        )
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        take(&mut self.root, &mut |(current_key, _): &(K, V)| {
+            current_key.borrow().cmp(key)
+        })
+        .map(|(_, value)| value)
+    }
+
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn iter(&self) -> AvlTreeMapIter<'_, K, V, S> {
+        AvlTreeMapIter::new(&self.root)
+    }
+
+    // Summary of all entries, cached at the root
+    pub fn summary(&self) -> S {
+        self.root
+            .as_ref()
+            .map_or_else(S::empty, |node| node.summary.clone())
+    }
+
+    // Summary of the entries whose keys lie in the range, in O(log n)
+    pub fn fold_range<Q, R>(&self, range: R) -> S
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let (lower, upper) = (range.start_bound(), range.end_bound());
+        assert_range(&Natural, lower, upper, "AugmentedAvlTreeMap");
+
+        let mut after_lower = |(key, _): &(K, V)| is_after(&Natural, key.borrow(), lower);
+        let mut before_upper = |(key, _): &(K, V)| is_before(&Natural, key.borrow(), upper);
+
+        fold(
+            &self.root,
+            Some(&mut after_lower).filter(|_| !matches!(lower, Bound::Unbounded)),
+            Some(&mut before_upper).filter(|_| !matches!(upper, Bound::Unbounded)),
+        )
+    }
+}
+
+impl<'a, K: Ord, V, S: Summary<(K, V)>> IntoIterator for &'a AugmentedAvlTreeMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = AvlTreeMapIter<'a, K, V, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K: Ord, V, S: Summary<(K, V)>> FromIterator<(K, V)> for AugmentedAvlTreeMap<K, V, S> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::AvlNode;
+    use crate::testing::Rng;
+    use std::collections::BTreeMap;
+
+    // Count, sum and maximum of the values in one go
+    #[derive(Debug, Clone, PartialEq)]
+    struct Stats {
+        count: usize,
+        sum: i64,
+        max: Option<i64>,
+    }
+
+    impl Stats {
+        const EMPTY: Stats = Stats {
+            count: 0,
+            sum: 0,
+            max: None,
+        };
+    }
+
+    impl Summary<(u64, i64)> for Stats {
+        fn empty() -> Self {
+            Stats::EMPTY
+        }
+
+        fn of((_, value): &(u64, i64)) -> Self {
+            Stats {
+                count: 1,
+                sum: *value,
+                max: Some(*value),
+            }
+        }
+
+        fn combine(&self, other: &Self) -> Self {
+            Stats {
+                count: self.count + other.count,
+                sum: self.sum + other.sum,
+                max: self.max.max(other.max),
+            }
+        }
+    }
+
+    fn brute_force<'a>(entries: impl Iterator<Item = (&'a u64, &'a i64)>) -> Stats {
+        entries.fold(Stats::EMPTY, |stats, (&key, &value)| {
+            stats.combine(&Stats::of(&(key, value)))
+        })
+    }
+
+    // Recomputes every cached summary from scratch and compares
+    fn check_summaries(tree: &AvlTree<(u64, i64), Stats>) -> Stats {
+        match tree {
+            None => Stats::EMPTY,
+            Some(node) => {
+                let AvlNode {
+                    value,
+                    left,
+                    right,
+                    summary,
+                    ..
+                } = &**node;
+                let actual = check_summaries(left)
+                    .combine(&Stats::of(value))
+                    .combine(&check_summaries(right));
+                assert_eq!(summary, &actual);
+
+                actual
+            }
+        }
+    }
+
+    #[test]
+    fn range_sums_over_time_keyed_metrics() {
+        // Requests per second, keyed by timestamp
+        let metrics: AugmentedAvlTreeMap<u64, i64, Stats> =
+            vec![(100, 3), (101, 7), (103, 2), (104, 9), (106, 1)]
+                .into_iter()
+                .collect();
+
+        assert_eq!(metrics.fold_range(101..=104).sum, 18);
+        assert_eq!(metrics.fold_range(101..104).max, Some(7));
+        assert_eq!(metrics.fold_range(105..).count, 1);
+        assert_eq!(metrics.fold_range(..100), Stats::EMPTY);
+        assert_eq!(metrics.fold_range(102..103), Stats::EMPTY);
+        assert_eq!(metrics.fold_range(..), metrics.summary());
+        assert_eq!(
+            metrics.fold_range((Bound::Excluded(100), Bound::Excluded(106))),
+            Stats {
+                count: 3,
+                sum: 18,
+                max: Some(9)
+            }
+        );
+    }
+
+    #[test]
+    fn insert_modify_and_remove_keep_summaries() {
+        let mut map: AugmentedAvlTreeMap<u64, i64, Stats> = AugmentedAvlTreeMap::new();
+
+        for i in 0..100 {
+            assert_eq!(map.insert(i, i as i64), None);
+        }
+
+        assert_eq!(map.insert(50, -50), Some(50));
+        assert_eq!(map.modify(&10, |value| replace(value, 1000)), Some(10));
+        assert_eq!(map.modify(&100, |value| *value += 1), None);
+        assert_eq!(map.remove(&99), Some(99));
+        assert_eq!(map.remove(&99), None);
+
+        check_summaries(&map.root);
+        assert_eq!(map.len(), 99);
+        assert_eq!(map.get(&50), Some(&-50));
+        assert_eq!(map.summary().sum, (0..99).sum::<i64>() - 100 + 990);
+        assert_eq!(map.summary().max, Some(1000));
+        assert_eq!(map.fold_range(11..).max, Some(98));
+    }
+
+    #[test]
+    fn folds_match_brute_force() {
+        let mut map: AugmentedAvlTreeMap<u64, i64, Stats> = AugmentedAvlTreeMap::new();
+        let mut model = BTreeMap::new();
+        let mut rng = Rng(12_345);
+        let mut next = |bound| rng.below(bound);
+
+        for _ in 0..3000 {
+            let key = next(256);
+            let value = next(1000) as i64 - 500;
+
+            match next(3) {
+                0 | 1 => assert_eq!(map.insert(key, value), model.insert(key, value)),
+                _ => assert_eq!(map.remove(&key), model.remove(&key)),
+            }
+
+            let (start, end) = (next(260), next(260));
+            let (start, end) = (start.min(end), start.max(end));
+
+            assert_eq!(
+                map.fold_range(start..end),
+                brute_force(model.range(start..end))
+            );
+            assert_eq!(
+                map.fold_range(start..=end),
+                brute_force(model.range(start..=end))
+            );
+            assert_eq!(map.fold_range(..end), brute_force(model.range(..end)));
+            assert_eq!(map.fold_range(start..), brute_force(model.range(start..)));
+        }
+
+        check_summaries(&map.root);
+        assert!(map.iter().eq(model.iter()));
+    }
+}
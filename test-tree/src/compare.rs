@@ -0,0 +1,72 @@
+use std::cmp::Ordering;
+use std::ops::Bound;
+
+// Orders the values of a set. Lookups with a borrowed form `Q` of the values
+// need the comparator to order `Q` too, which `Natural` does for any `Q: Ord`.
+pub trait Compare<T: ?Sized> {
+    fn compare(&self, left: &T, right: &T) -> Ordering;
+}
+
+// The natural order given by `Ord`, used when no comparator is given
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Natural;
+
+impl<T: Ord + ?Sized> Compare<T> for Natural {
+    fn compare(&self, left: &T, right: &T) -> Ordering {
+        left.cmp(right)
+    }
+}
+
+// Any closure ordering two values works as a comparator, for example
+// `|a: &String, b: &String| a.to_lowercase().cmp(&b.to_lowercase())`
+impl<T: ?Sized, F: Fn(&T, &T) -> Ordering> Compare<T> for F {
+    fn compare(&self, left: &T, right: &T) -> Ordering {
+        self(left, right)
+    }
+}
+
+// Same sanity checks on range bounds as the std collections, the panic message
+// names the collection the range was given to
+pub(crate) fn assert_range<Q: ?Sized, C: Compare<Q>>(
+    cmp: &C,
+    lower: Bound<&Q>,
+    upper: Bound<&Q>,
+    collection: &str,
+) {
+    match (lower, upper) {
+        (Bound::Excluded(start), Bound::Excluded(end))
+            if cmp.compare(start, end) == Ordering::Equal =>
+        {
+            panic!(
+                "range start and end are equal and excluded in {}",
+                collection
+            )
+        }
+
+        (
+            Bound::Included(start) | Bound::Excluded(start),
+            Bound::Included(end) | Bound::Excluded(end),
+        ) if cmp.compare(start, end) == Ordering::Greater => {
+            panic!("range start is greater than range end in {}", collection)
+        }
+
+        _ => {}
+    }
+}
+
+// Does the value lie on the inner side of a lower or upper range bound
+pub(crate) fn is_after<Q: ?Sized, C: Compare<Q>>(cmp: &C, value: &Q, bound: Bound<&Q>) -> bool {
+    match bound {
+        Bound::Included(bound) => cmp.compare(value, bound) != Ordering::Less,
+        Bound::Excluded(bound) => cmp.compare(value, bound) == Ordering::Greater,
+        Bound::Unbounded => true,
+    }
+}
+
+pub(crate) fn is_before<Q: ?Sized, C: Compare<Q>>(cmp: &C, value: &Q, bound: Bound<&Q>) -> bool {
+    match bound {
+        Bound::Included(bound) => cmp.compare(value, bound) != Ordering::Greater,
+        Bound::Excluded(bound) => cmp.compare(value, bound) == Ordering::Less,
+        Bound::Unbounded => true,
+    }
+}
@@ -0,0 +1,279 @@
+use std::borrow::Borrow;
+use std::fmt::{self, Debug};
+use std::iter::FromIterator;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use arc_swap::ArcSwap;
+
+use crate::compare::{Compare, Natural};
+use crate::persistent::PersistentAvlTreeSet;
+
+// Ordered set shared between threads. Every update publishes a new version of
+// a persistent tree, so readers work on whatever version was current when they
+// started and never wait for an update to be built.
+//
+// The current version is swapped atomically, reading it takes no lock and
+// usually does not touch its reference count either. Every `contains`, `len`
+// or `is_empty` loads the current version again, so several calls can see
+// different versions. Queries that have to agree with each other, or many of
+// them in a row, should go to a `snapshot` taken once instead.
+//
+// Writers on the other hand take turns. Letting them build their versions in
+// parallel and retry on conflict would run the update closure more than once,
+// so updates are serialized and only scale with how fast each one is built.
+//
+// A panicking writer cannot leave a half updated tree behind, it only fails to
+// publish its version, so a poisoned writer lock is simply taken over.
+pub struct ConcurrentAvlSet<T, C = Natural> {
+    published: ArcSwap<PersistentAvlTreeSet<T, C>>,
+    // Two writers starting from the same version would otherwise lose one of
+    // the updates
+    writer: Mutex<()>,
+}
+
+impl<T: Debug, C: Compare<T>> Debug for ConcurrentAvlSet<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.published.load().fmt(f)
+    }
+}
+
+impl<T, C: Compare<T> + Default> Default for ConcurrentAvlSet<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T: Ord> ConcurrentAvlSet<T> {
+    pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+}
+
+impl<T, C: Compare<T>> ConcurrentAvlSet<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        Self::from(PersistentAvlTreeSet::with_comparator(cmp))
+    }
+
+    // The current version of the set in O(1), later updates do not show up in
+    // it
+    pub fn snapshot(&self) -> PersistentAvlTreeSet<T, C> {
+        PersistentAvlTreeSet::clone(&self.published.load())
+    }
+
+    // Builds the next version out of the current one and publishes it, all
+    // changes made by the closure become visible at once. Returns whether the
+    // closure changed anything.
+    pub fn update<F>(&self, f: F) -> bool
+    where
+        F: FnOnce(&PersistentAvlTreeSet<T, C>) -> PersistentAvlTreeSet<T, C>,
+    {
+        let _writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let current = self.published.load_full();
+        let next = f(&current);
+
+        if next.ptr_eq(&current) {
+            return false;
+        }
+
+        self.published.store(Arc::new(next));
+
+        true
+    }
+
+    pub fn insert(&self, value: T) -> bool
+    where
+        T: Clone,
+    {
+        self.update(|set| set.insert(value))
+    }
+
+    pub fn remove<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q> + Clone,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.update(|set| set.remove(value))
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.published.load().contains(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.published.load().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.published.load().is_empty()
+    }
+}
+
+impl<T, C> From<PersistentAvlTreeSet<T, C>> for ConcurrentAvlSet<T, C> {
+    fn from(set: PersistentAvlTreeSet<T, C>) -> Self {
+        Self {
+            published: ArcSwap::from_pointee(set),
+            writer: Mutex::new(()),
+        }
+    }
+}
+
+impl<T: Clone, C: Compare<T> + Default> FromIterator<T> for ConcurrentAvlSet<T, C> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<PersistentAvlTreeSet<T, C>>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Rng;
+    use std::collections::BTreeSet;
+    use std::sync::Arc;
+    use std::thread;
+
+    const THREADS: u32 = 8;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn is_send_and_sync() {
+        assert_send_sync::<ConcurrentAvlSet<u32>>();
+        assert_send_sync::<ConcurrentAvlSet<String, fn(&String, &String) -> std::cmp::Ordering>>();
+    }
+
+    #[test]
+    fn snapshot_keeps_its_version() {
+        let set: ConcurrentAvlSet<u32> = (0..100).collect();
+        let snapshot = set.snapshot();
+
+        assert!(set.remove(&0));
+        assert!(set.insert(100));
+
+        assert!(!set.contains(&0) && set.contains(&100));
+        assert!(snapshot.contains(&0) && !snapshot.contains(&100));
+        assert!(snapshot.iter().copied().eq(0..100));
+        assert_eq!(format!("{:?}", set), format!("{:?}", set.snapshot()));
+    }
+
+    #[test]
+    fn mixed_operations_under_contention() {
+        let set = Arc::new(ConcurrentAvlSet::new());
+
+        // Every thread updates its own keys but looks up everybody's
+        let workers: Vec<_> = (0..THREADS)
+            .map(|thread_index| {
+                let set = Arc::clone(&set);
+
+                thread::spawn(move || {
+                    let mut expected = BTreeSet::new();
+                    let mut rng = Rng(1 + u64::from(thread_index));
+
+                    for step in 0..3000 {
+                        let key = rng.below(512) as u32;
+                        let own_key = key / THREADS * THREADS + thread_index;
+
+                        match step % 4 {
+                            0 => assert_eq!(set.remove(&own_key), expected.remove(&own_key)),
+                            1 => {
+                                set.contains(&key);
+                            }
+                            _ => assert_eq!(set.insert(own_key), expected.insert(own_key)),
+                        }
+
+                        assert_eq!(set.contains(&own_key), expected.contains(&own_key));
+                    }
+
+                    expected
+                })
+            })
+            .collect();
+
+        let expected: BTreeSet<u32> = workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect();
+        let snapshot = set.snapshot();
+
+        assert!(snapshot.is_balanced());
+        assert!(snapshot.iter().eq(expected.iter()));
+    }
+
+    #[test]
+    fn no_update_gets_lost() {
+        let set = Arc::new(ConcurrentAvlSet::new());
+
+        // All threads race for the same values, every value that went in must
+        // have come out exactly once
+        let workers: Vec<_> = (0..THREADS)
+            .map(|thread_index| {
+                let set = Arc::clone(&set);
+
+                thread::spawn(move || {
+                    let values: Vec<u32> =
+                        (0..1000).map(|i| (i * 7 + thread_index) % 1000).collect();
+                    let inserted = values.iter().filter(|&&value| set.insert(value)).count();
+                    let removed = values.iter().filter(|&&value| set.remove(&value)).count();
+
+                    (inserted, removed)
+                })
+            })
+            .collect();
+
+        let counts: Vec<(usize, usize)> = workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .collect();
+        let inserted: usize = counts.iter().map(|(inserted, _)| inserted).sum();
+        let removed: usize = counts.iter().map(|(_, removed)| removed).sum();
+
+        assert!(inserted >= 1000);
+        assert_eq!(inserted, removed);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn readers_see_consistent_snapshots() {
+        let set = Arc::new(ConcurrentAvlSet::new());
+
+        let readers: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let set = Arc::clone(&set);
+
+                thread::spawn(move || {
+                    let mut last_len = 0;
+
+                    while last_len < 2000 {
+                        // Values go in ascending order in batches of ten, so
+                        // every version holds a prefix of whole batches
+                        let snapshot = set.snapshot();
+                        let len = snapshot.len();
+
+                        assert!(len >= last_len && len % 10 == 0);
+                        assert!(snapshot.iter().copied().eq(0..len as u32));
+                        last_len = len;
+                    }
+                })
+            })
+            .collect();
+
+        for batch in 0..200 {
+            assert!(set.update(|snapshot| {
+                (batch * 10..(batch + 1) * 10)
+                    .fold(snapshot.clone(), |next, value| next.insert(value))
+            }));
+        }
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        assert!(!set.update(|snapshot| snapshot.insert(0)));
+        assert_eq!(set.len(), 2000);
+    }
+}
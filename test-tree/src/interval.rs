@@ -0,0 +1,441 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Debug};
+use std::iter::FromIterator;
+use std::mem::replace;
+use std::ops::{Bound, Range};
+
+use crate::compare::{is_before, Natural};
+use crate::node::{find, find_mut, insert, size, take, AvlNode, AvlTree};
+use crate::summary::Summary;
+
+// Greatest end of the intervals in a subtree, which tells whether anything
+// in there can still reach a given point
+#[derive(Debug, Clone)]
+struct MaxEnd<K>(Option<K>);
+
+impl<K: Ord + Clone, V> Summary<(Range<K>, V)> for MaxEnd<K> {
+    fn empty() -> Self {
+        MaxEnd(None)
+    }
+
+    fn of((interval, _): &(Range<K>, V)) -> Self {
+        MaxEnd(Some(interval.end.clone()))
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        MaxEnd(self.0.as_ref().max(other.0.as_ref()).cloned())
+    }
+}
+
+type IntervalNode<K, V> = AvlNode<(Range<K>, V), MaxEnd<K>>;
+
+// Intervals are ordered by their start and then by their end
+fn cmp_intervals<K: Ord>(a: &Range<K>, b: &Range<K>) -> Ordering {
+    a.start.cmp(&b.start).then_with(|| a.end.cmp(&b.end))
+}
+
+// Does some interval of the subtree end after the point
+fn ends_after<K: Ord, V>(tree: &AvlTree<(Range<K>, V), MaxEnd<K>>, point: &K) -> bool {
+    tree.as_ref()
+        .is_some_and(|node| node.summary.0.as_ref().is_some_and(|end| end > point))
+}
+
+// Map from half-open intervals to values. Every node caches the greatest end
+// in its subtree, so queries skip the subtrees that end too early. The k
+// matching intervals can lie anywhere in the start order, and the walk visits
+// the paths down to all of them, which takes O(k log(n / k) + log n).
+#[derive(Clone)]
+pub struct IntervalTree<K: Ord, V> {
+    root: AvlTree<(Range<K>, V), MaxEnd<K>>,
+}
+
+impl<K: Ord + Debug, V: Debug> Debug for IntervalTree<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut iter = IntervalTreeIter {
+            prev_nodes: Vec::new(),
+        };
+        iter.push_left(&self.root);
+
+        f.debug_map().entries(iter).finish()
+    }
+}
+
+impl<K: Ord + Clone, V> Default for IntervalTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Clone, V> IntervalTree<K, V> {
+    pub fn new() -> Self {
+        Self { root: None }
+    }
+
+    // Empty intervals would never overlap anything, so they are rejected
+    pub fn insert(&mut self, interval: Range<K>, value: V) -> Option<V> {
+        assert!(
+            interval.start < interval.end,
+            "interval start is not less than its end in IntervalTree"
+        );
+
+        match insert(&mut self.root, (interval, value), &mut |a, b| {
+            cmp_intervals(&a.0, &b.0)
+        }) {
+            Ok(_) => None,
+
+            // The value plays no part in the cached ends, so it can be
+            // replaced in place
+            Err((interval, value)) => self
+                .get_mut(&interval)
+                .map(|current_value| replace(current_value, value)),
+        }
+    }
+
+    pub fn get(&self, interval: &Range<K>) -> Option<&V> {
+        find(&self.root, |(current, _)| cmp_intervals(current, interval)).map(|(_, value)| value)
+    }
+
+    pub fn get_mut(&mut self, interval: &Range<K>) -> Option<&mut V> {
+        find_mut(&mut self.root, |(current, _)| {
+            cmp_intervals(current, interval)
+        })
+        .map(|(_, value)| value)
+    }
+
+    pub fn contains(&self, interval: &Range<K>) -> bool {
+        self.get(interval).is_some()
+    }
+
+    pub fn remove(&mut self, interval: &Range<K>) -> Option<V> {
+        take(&mut self.root, &mut |(current, _): &(Range<K>, V)| {
+            cmp_intervals(current, interval)
+        })
+        .map(|(_, value)| value)
+    }
+
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn iter(&self) -> IntervalTreeIter<'_, K, V> {
+        let mut iter = IntervalTreeIter {
+            prev_nodes: Vec::new(),
+        };
+        iter.push_left(&self.root);
+
+        iter
+    }
+
+    // Intervals sharing at least one point with the range, by increasing start,
+    // which is none at all for an empty range. The bounds are cloned, so the
+    // range does not have to outlive the iterator.
+    pub fn overlapping(&self, range: &Range<K>) -> Overlapping<'_, K, V> {
+        let mut overlapping = Overlapping {
+            prev_nodes: Vec::new(),
+            after: range.start.clone(),
+            before: Bound::Excluded(range.end.clone()),
+        };
+
+        if range.start < range.end {
+            overlapping.push_left(&self.root);
+        }
+
+        overlapping
+    }
+
+    // Intervals the point lies in, by increasing start
+    pub fn containing(&self, point: &K) -> Overlapping<'_, K, V> {
+        let mut overlapping = Overlapping {
+            prev_nodes: Vec::new(),
+            after: point.clone(),
+            before: Bound::Included(point.clone()),
+        };
+        overlapping.push_left(&self.root);
+
+        overlapping
+    }
+
+    // The interval with the lowest start that contains the point, if any, in
+    // O(log n). This answers whether the point is covered at all, `containing`
+    // yields every interval that covers it. When the left subtree ends after
+    // the point but holds no interval containing it, all its intervals reaching
+    // past the point start after it, and so does the whole right subtree, so a
+    // single path has to be walked.
+    pub fn first_containing(&self, point: &K) -> Option<(&Range<K>, &V)> {
+        let mut tree = &self.root;
+        let mut found = None;
+
+        while let Some(current_node) = tree {
+            let (interval, value) = &current_node.value;
+            let contains = interval.start <= *point && *point < interval.end;
+
+            if contains {
+                found = Some((interval, value));
+            }
+
+            // Going left keeps the node as the answer in case the left subtree
+            // has none, going right only happens when the node does not count
+            if ends_after(&current_node.left, point) {
+                tree = &current_node.left;
+            } else if contains || interval.start > *point {
+                break;
+            } else {
+                tree = &current_node.right;
+            }
+        }
+
+        found
+    }
+}
+
+impl<'a, K: Ord + Clone, V> IntoIterator for &'a IntervalTree<K, V> {
+    type Item = (&'a Range<K>, &'a V);
+    type IntoIter = IntervalTreeIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K: Ord + Clone, V> FromIterator<(Range<K>, V)> for IntervalTree<K, V> {
+    fn from_iter<I: IntoIterator<Item = (Range<K>, V)>>(iter: I) -> Self {
+        let mut tree = Self::new();
+
+        for (interval, value) in iter {
+            tree.insert(interval, value);
+        }
+
+        tree
+    }
+}
+
+pub struct IntervalTreeIter<'a, K: Ord, V> {
+    prev_nodes: Vec<&'a IntervalNode<K, V>>,
+}
+
+impl<'a, K: Ord, V> Clone for IntervalTreeIter<'a, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            prev_nodes: self.prev_nodes.clone(),
+        }
+    }
+}
+
+// Iterators print the entries they have left rather than the nodes they hold
+impl<'a, K: Ord + Debug, V: Debug> Debug for IntervalTreeIter<'a, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, K: Ord, V> IntervalTreeIter<'a, K, V> {
+    fn push_left(&mut self, mut tree: &'a AvlTree<(Range<K>, V), MaxEnd<K>>) {
+        while let Some(current_node) = tree {
+            self.prev_nodes.push(current_node);
+            tree = &current_node.left;
+        }
+    }
+}
+
+impl<'a, K: Ord, V> Iterator for IntervalTreeIter<'a, K, V> {
+    type Item = (&'a Range<K>, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current_node = self.prev_nodes.pop()?;
+        self.push_left(&current_node.right);
+
+        let (interval, value) = &current_node.value;
+
+        Some((interval, value))
+    }
+}
+
+// In-order walk over the intervals that end after `after` and start before
+// `before`, leaving out the subtrees that end too early and stopping at the
+// first interval that starts too late
+pub struct Overlapping<'a, K: Ord, V> {
+    prev_nodes: Vec<&'a IntervalNode<K, V>>,
+    after: K,
+    before: Bound<K>,
+}
+
+impl<'a, K: Ord + Clone, V> Clone for Overlapping<'a, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            prev_nodes: self.prev_nodes.clone(),
+            after: self.after.clone(),
+            before: self.before.clone(),
+        }
+    }
+}
+
+impl<'a, K: Ord + Clone + Debug, V: Debug> Debug for Overlapping<'a, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, K: Ord, V> Overlapping<'a, K, V> {
+    fn push_left(&mut self, mut tree: &'a AvlTree<(Range<K>, V), MaxEnd<K>>) {
+        while let Some(current_node) = tree {
+            if current_node.summary.0.as_ref() <= Some(&self.after) {
+                break;
+            }
+
+            self.prev_nodes.push(current_node);
+            tree = &current_node.left;
+        }
+    }
+}
+
+impl<'a, K: Ord, V> Iterator for Overlapping<'a, K, V> {
+    type Item = (&'a Range<K>, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(current_node) = self.prev_nodes.pop() {
+            let (interval, value) = &current_node.value;
+
+            if !is_before(&Natural, &interval.start, self.before.as_ref()) {
+                self.prev_nodes.clear();
+                break;
+            }
+
+            self.push_left(&current_node.right);
+
+            if interval.end > self.after {
+                return Some((interval, value));
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Rng;
+
+    // Recomputes the greatest end of every subtree and compares
+    fn check_max_ends(tree: &AvlTree<(Range<u32>, u32), MaxEnd<u32>>) -> Option<u32> {
+        let node = tree.as_ref()?;
+        let actual = check_max_ends(&node.left)
+            .max(Some(node.value.0.end))
+            .max(check_max_ends(&node.right));
+        assert_eq!(node.summary.0, actual);
+
+        actual
+    }
+
+    #[test]
+    fn maintenance_windows() {
+        let mut windows = IntervalTree::new();
+        windows.insert(900..1100, "database");
+        windows.insert(1000..1030, "cache");
+        windows.insert(1100..1200, "network");
+        windows.insert(1300..1400, "storage");
+
+        let names = |found: Overlapping<'_, u32, &'static str>| {
+            found.map(|(_, name)| *name).collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(windows.overlapping(&(1015..1100))),
+            ["database", "cache"]
+        );
+        assert_eq!(names(windows.overlapping(&(1100..1300))), ["network"]);
+        assert_eq!(
+            names(windows.overlapping(&(1200..1300))),
+            Vec::<&str>::new()
+        );
+        assert_eq!(names(windows.containing(&1100)), ["network"]);
+        assert_eq!(names(windows.containing(&1020)), ["database", "cache"]);
+        assert_eq!(
+            windows.first_containing(&1020),
+            Some((&(900..1100), &"database"))
+        );
+        assert_eq!(windows.first_containing(&1250), None);
+
+        // The query range does not have to outlive the iterator
+        let mut later = windows.overlapping(&(1150..1350));
+        assert_eq!(later.next(), Some((&(1100..1200), &"network")));
+        assert_eq!(later.count(), 1);
+
+        assert_eq!(windows.insert(1000..1030, "caches"), Some("cache"));
+        assert_eq!(windows.remove(&(900..1100)), Some("database"));
+        assert_eq!(windows.remove(&(900..1100)), None);
+        assert_eq!(
+            windows.first_containing(&1020),
+            Some((&(1000..1030), &"caches"))
+        );
+        assert_eq!(windows.len(), 3);
+    }
+
+    #[test]
+    fn queries_match_brute_force() {
+        let mut tree = IntervalTree::new();
+        let mut model: Vec<(Range<u32>, u32)> = Vec::new();
+        let mut rng = Rng(42);
+        let mut next = |bound| rng.below(bound) as u32;
+
+        for step in 0..2000 {
+            let start = next(500);
+            let interval = start..start + 1 + next(40);
+
+            if next(3) == 0 {
+                let position = model.iter().position(|(current, _)| *current == interval);
+                let expected = position.map(|position| model.remove(position).1);
+
+                assert_eq!(tree.remove(&interval), expected);
+            } else {
+                let expected = match model.iter_mut().find(|(current, _)| *current == interval) {
+                    Some((_, value)) => Some(replace(value, step)),
+                    None => {
+                        model.push((interval.clone(), step));
+                        None
+                    }
+                };
+
+                assert_eq!(tree.insert(interval, step), expected);
+            }
+
+            model.sort_by(|a, b| cmp_intervals(&a.0, &b.0));
+
+            let start = next(560);
+            let query = start..start + next(60);
+            let overlapping: Vec<_> = model
+                .iter()
+                .filter(|(current, _)| {
+                    // Empty ranges have no point in common with anything
+                    !query.is_empty() && current.start < query.end && query.start < current.end
+                })
+                .map(|(current, value)| (current, value))
+                .collect();
+            assert_eq!(tree.overlapping(&query).collect::<Vec<_>>(), overlapping);
+
+            let point = next(560);
+            let containing: Vec<_> = model
+                .iter()
+                .filter(|(current, _)| current.contains(&point))
+                .map(|(current, value)| (current, value))
+                .collect();
+            assert_eq!(tree.containing(&point).collect::<Vec<_>>(), containing);
+            assert_eq!(tree.first_containing(&point), containing.first().copied());
+        }
+
+        check_max_ends(&tree.root);
+        assert!(tree
+            .iter()
+            .eq(model.iter().map(|(current, value)| (current, value))));
+    }
+
+    #[test]
+    #[should_panic(expected = "interval start is not less than its end in IntervalTree")]
+    fn empty_intervals_are_rejected() {
+        IntervalTree::new().insert(5..5, ());
+    }
+}
@@ -0,0 +1,36 @@
+#[macro_use]
+mod sorted;
+
+mod arena;
+mod augmented;
+mod compare;
+mod concurrent;
+mod interval;
+mod map;
+mod multiset;
+mod node;
+mod persistent;
+mod set;
+mod summary;
+#[cfg(test)]
+mod testing;
+
+pub use arena::{
+    ArenaAvlTreeSet, ArenaAvlTreeSetIntoIter, ArenaAvlTreeSetIter, ArenaAvlTreeSetParIter,
+    ArenaCursor, ArenaCursorMut, ArenaExtractIf,
+};
+pub use augmented::AugmentedAvlTreeMap;
+pub use compare::{Compare, Natural};
+pub use concurrent::ConcurrentAvlSet;
+pub use interval::{IntervalTree, IntervalTreeIter, Overlapping};
+pub use map::{AvlTreeMap, AvlTreeMapIter, AvlTreeMapIterMut, Entry, OccupiedEntry, VacantEntry};
+pub use multiset::{AvlTreeMultiSet, AvlTreeMultiSetCounts, AvlTreeMultiSetIter};
+pub use persistent::{PersistentAvlTreeSet, PersistentAvlTreeSetIter};
+pub use set::{
+    AvlTreeSet, AvlTreeSetIntoIter, AvlTreeSetIter, AvlTreeSetParIter, Cursor, CursorMut,
+    Difference, ExtractIf, Intersection, SortedInput, SymmetricDifference, Union, UnsortedError,
+};
+pub use summary::Summary;
+
+#[cfg(debug_assertions)]
+pub use set::{InvariantError, Violation};
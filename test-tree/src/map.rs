@@ -0,0 +1,536 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt::{self, Debug};
+use std::mem::replace;
+
+use crate::node::{
+    find, find_mut, insert, insert_vacant, search, select, select_mut, take, take_at, AvlNode,
+    AvlTree, Vacancy,
+};
+
+// The map stores its entries as key-value pairs ordered by the key alone
+pub(crate) fn cmp_keys<K: Ord, V>(a: &(K, V), b: &(K, V)) -> Ordering {
+    a.0.cmp(&b.0)
+}
+
+#[derive(Clone)]
+pub struct AvlTreeMap<K: Ord, V> {
+    root: AvlTree<(K, V)>,
+}
+
+// Maps are compared and formatted by their entries in key order, whatever
+// shape the insertions left the tree in
+
+impl<K: Ord, V: PartialEq> PartialEq for AvlTreeMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<K: Ord, V: Eq> Eq for AvlTreeMap<K, V> {}
+
+impl<K: Ord + Debug, V: Debug> Debug for AvlTreeMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Ord, V> Default for AvlTreeMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> AvlTreeMap<K, V> {
+    pub fn new() -> Self {
+        Self { root: None }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match insert(&mut self.root, (key, value), &mut cmp_keys) {
+            Ok(_) => None,
+
+            // The key is already present, keep it and replace the value only
+            Err((key, value)) => self
+                .get_mut(&key)
+                .map(|current_value| replace(current_value, value)),
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        find(&self.root, |(current_key, _)| current_key.borrow().cmp(key)).map(|(_, value)| value)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        find_mut(&mut self.root, |(current_key, _)| {
+            current_key.borrow().cmp(key)
+        })
+        .map(|(_, value)| value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        take(&mut self.root, &mut |(current_key, _): &(K, V)| {
+            current_key.borrow().cmp(key)
+        })
+        .map(|(_, value)| value)
+    }
+
+    // Searches the key once, the entry remembers where it ended up
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        match search(&self.root, |(current_key, _)| current_key.cmp(&key)) {
+            Ok(index) => Entry::Occupied(OccupiedEntry { map: self, index }),
+            Err(vacancy) => Entry::Vacant(VacantEntry {
+                map: self,
+                key,
+                vacancy,
+            }),
+        }
+    }
+
+    pub fn iter(&self) -> AvlTreeMapIter<'_, K, V> {
+        AvlTreeMapIter::new(&self.root)
+    }
+
+    pub fn iter_mut(&mut self) -> AvlTreeMapIterMut<'_, K, V> {
+        let mut iter = AvlTreeMapIterMut {
+            prev_entries: Vec::new(),
+        };
+        iter.push_left(&mut self.root);
+
+        iter
+    }
+}
+
+pub enum Entry<'a, K: Ord, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+impl<'a, K: Ord, V> Entry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(ref mut entry) = self {
+            f(entry.get_mut());
+        }
+
+        self
+    }
+}
+
+impl<'a, K: Ord, V: Default> Entry<'a, K, V> {
+    pub fn or_default(self) -> &'a mut V {
+        self.or_insert_with(V::default)
+    }
+}
+
+// The entry keeps the position of its key, which stays valid as long as the
+// entry borrows the map. Like for `BTreeMap`, the probe key is dropped and the
+// key stored in the map is kept.
+pub struct OccupiedEntry<'a, K: Ord, V> {
+    map: &'a mut AvlTreeMap<K, V>,
+    index: usize,
+}
+
+impl<'a, K: Ord, V> OccupiedEntry<'a, K, V> {
+    fn pair(&self) -> &(K, V) {
+        select(&self.map.root, self.index).unwrap()
+    }
+
+    pub fn key(&self) -> &K {
+        &self.pair().0
+    }
+
+    pub fn get(&self) -> &V {
+        &self.pair().1
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut select_mut(&mut self.map.root, self.index).unwrap().1
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        &mut select_mut(&mut self.map.root, self.index).unwrap().1
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        replace(self.get_mut(), value)
+    }
+
+    pub fn remove_entry(self) -> (K, V) {
+        take_at(&mut self.map.root, self.index).unwrap()
+    }
+
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+}
+
+pub struct VacantEntry<'a, K: Ord, V> {
+    map: &'a mut AvlTreeMap<K, V>,
+    key: K,
+    vacancy: Vacancy,
+}
+
+impl<'a, K: Ord, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    // Inserts on the path the search of the entry took, without comparing keys
+    // again
+    pub fn insert(self, value: V) -> &'a mut V {
+        &mut insert_vacant(&mut self.map.root, self.vacancy, (self.key, value)).1
+    }
+}
+
+pub struct AvlTreeMapIter<'a, K: Ord, V, S = ()> {
+    prev_nodes: Vec<&'a AvlNode<(K, V), S>>,
+}
+
+impl<'a, K: Ord, V, S> Clone for AvlTreeMapIter<'a, K, V, S> {
+    fn clone(&self) -> Self {
+        Self {
+            prev_nodes: self.prev_nodes.clone(),
+        }
+    }
+}
+
+// Iterators print the entries they have left rather than the nodes they hold
+impl<'a, K: Ord + Debug, V: Debug, S> Debug for AvlTreeMapIter<'a, K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, K: Ord, V, S> AvlTreeMapIter<'a, K, V, S> {
+    pub(crate) fn new(root: &'a AvlTree<(K, V), S>) -> Self {
+        let mut iter = Self {
+            prev_nodes: Vec::new(),
+        };
+        iter.push_left(root);
+
+        iter
+    }
+
+    fn push_left(&mut self, mut tree: &'a AvlTree<(K, V), S>) {
+        while let Some(current_node) = tree {
+            self.prev_nodes.push(current_node);
+            tree = &current_node.left;
+        }
+    }
+}
+
+impl<'a, K: Ord, V, S> Iterator for AvlTreeMapIter<'a, K, V, S> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current_node = self.prev_nodes.pop()?;
+        self.push_left(&current_node.right);
+
+        let (key, value) = &current_node.value;
+
+        Some((key, value))
+    }
+}
+
+// A visited entry split from the right subtree that still has to be walked
+type SplitNode<'a, K, V> = (&'a K, &'a mut V, &'a mut AvlTree<(K, V)>);
+
+pub struct AvlTreeMapIterMut<'a, K: Ord, V> {
+    prev_entries: Vec<SplitNode<'a, K, V>>,
+}
+
+impl<'a, K: Ord + Debug, V: Debug> Debug for AvlTreeMapIterMut<'a, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self
+            .prev_entries
+            .iter()
+            .rev()
+            .flat_map(|(key, value, right)| {
+                Some((*key, &**value))
+                    .into_iter()
+                    .chain(AvlTreeMapIter::new(&**right))
+            });
+
+        f.debug_list().entries(entries).finish()
+    }
+}
+
+impl<'a, K: Ord, V> AvlTreeMapIterMut<'a, K, V> {
+    fn push_left(&mut self, mut tree: &'a mut AvlTree<(K, V)>) {
+        // Split every node into disjoint borrows of its entry and right subtree
+        while let Some(current_node) = tree {
+            let AvlNode {
+                value: (key, value),
+                left,
+                right,
+                ..
+            } = &mut **current_node;
+
+            self.prev_entries.push((key, value, right));
+            tree = left;
+        }
+    }
+}
+
+impl<'a, K: Ord, V> Iterator for AvlTreeMapIterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value, right) = self.prev_entries.pop()?;
+        self.push_left(right);
+
+        Some((key, value))
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a AvlTreeMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = AvlTreeMapIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a mut AvlTreeMap<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = AvlTreeMapIterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::is_balanced;
+    use crate::testing::Rng;
+    use std::collections::BTreeMap;
+
+    // Orders by the first field only, so equal keys can still be told apart
+    #[derive(Debug)]
+    struct Tagged(u32, &'static str);
+
+    impl PartialEq for Tagged {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for Tagged {}
+
+    impl PartialOrd for Tagged {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Tagged {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+
+    #[test]
+    fn insert_get_and_replace() {
+        let mut map = AvlTreeMap::new();
+
+        for i in 0..100 {
+            assert_eq!(map.insert(i, i * 10), None);
+            assert!(is_balanced(map.root.as_deref()));
+        }
+
+        assert_eq!(map.insert(42, 0), Some(420));
+        assert_eq!(map.get(&42), Some(&0));
+        assert_eq!(map.get(&100), None);
+        assert!(map.contains_key(&99));
+        assert!(!map.contains_key(&-1));
+
+        *map.get_mut(&7).unwrap() += 1;
+        assert_eq!(map.get(&7), Some(&71));
+    }
+
+    #[test]
+    fn remove_keeps_balance() {
+        let mut map = AvlTreeMap::new();
+
+        for i in 0..100 {
+            map.insert(i, i.to_string());
+        }
+
+        for i in (0..100).step_by(2) {
+            assert_eq!(map.remove(&i), Some(i.to_string()));
+            assert_eq!(map.remove(&i), None);
+            assert!(is_balanced(map.root.as_deref()));
+        }
+
+        assert!(map.iter().map(|(key, _)| *key).eq((1..100).step_by(2)));
+    }
+
+    #[test]
+    fn entry_api() {
+        let mut map = AvlTreeMap::new();
+
+        // Count words, inserting and modifying through entries
+        for word in "a b a c b a".split(' ') {
+            map.entry(word).and_modify(|count| *count += 1).or_insert(1);
+        }
+
+        assert!(map.iter().eq(vec![(&"a", &3), (&"b", &2), (&"c", &1)]));
+
+        *map.entry("d").or_default() += 5;
+        assert_eq!(map.get("d"), Some(&5));
+
+        assert_eq!(*map.entry("d").or_insert_with(|| unreachable!()), 5);
+        assert_eq!(map.entry("e").key(), &"e");
+
+        match map.entry("a") {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), 3),
+            Entry::Vacant(_) => unreachable!(),
+        }
+
+        assert!(!map.contains_key(&"a"));
+    }
+
+    #[test]
+    fn vacant_entry_reference_survives_rebalancing() {
+        let mut map = AvlTreeMap::new();
+
+        // Every insert of an increasing key triggers rotations
+        for i in 0..100 {
+            let value = map.entry(i).or_insert(0);
+            *value = i * 2;
+        }
+
+        assert!(is_balanced(map.root.as_deref()));
+        assert!(map.iter().all(|(key, value)| *value == key * 2));
+    }
+
+    #[test]
+    fn vacant_entries_keep_the_tree_valid() {
+        // Random keys go through every rotation case the single pass handles
+        for seed in 0..20 {
+            let mut rng = Rng(seed);
+            let mut map = AvlTreeMap::new();
+            let mut model = BTreeMap::new();
+
+            for step in 0..500 {
+                let key = rng.below(300);
+
+                if step % 5 == 0 {
+                    assert_eq!(map.remove(&key), model.remove(&key));
+                } else {
+                    *map.entry(key).or_insert(step) += 1;
+                    *model.entry(key).or_insert(step) += 1;
+                }
+
+                assert!(
+                    is_balanced(map.root.as_deref()),
+                    "seed {} step {}",
+                    seed,
+                    step
+                );
+            }
+
+            assert!(map.iter().eq(model.iter()));
+        }
+    }
+
+    #[test]
+    fn occupied_entry_uses_the_stored_key() {
+        let mut map = AvlTreeMap::new();
+        map.insert(Tagged(1, "stored"), 10);
+        map.insert(Tagged(2, "other"), 20);
+
+        match map.entry(Tagged(1, "probe")) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.key().1, "stored");
+                assert_eq!(entry.insert(11), 10);
+                assert_eq!(*entry.get(), 11);
+                assert_eq!(entry.remove_entry(), (Tagged(1, "stored"), 11));
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+
+        assert!(map.iter().map(|(key, _)| key.1).eq(vec!["other"]));
+    }
+
+    #[test]
+    fn equality_ignores_tree_shape() {
+        let mut ascending = AvlTreeMap::new();
+        let mut descending = AvlTreeMap::new();
+
+        for i in 0..5 {
+            ascending.insert(i, i * 2);
+            descending.insert(4 - i, (4 - i) * 2);
+        }
+
+        assert_eq!(ascending, descending);
+        assert_eq!(
+            format!("{:?}", descending),
+            "{0: 0, 1: 2, 2: 4, 3: 6, 4: 8}"
+        );
+
+        descending.insert(4, 0);
+        assert_ne!(ascending, descending);
+    }
+
+    #[test]
+    fn iter_mut_in_key_order() {
+        let mut map = AvlTreeMap::new();
+
+        for i in (0..50).rev() {
+            map.insert(i, 0);
+        }
+
+        for (key, value) in &mut map {
+            *value = *key + 1;
+        }
+
+        assert!(map.iter().map(|(key, _)| *key).eq(0..50));
+        assert!(map.iter().all(|(key, value)| *value == key + 1));
+    }
+}
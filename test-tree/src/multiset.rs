@@ -0,0 +1,426 @@
+use std::borrow::Borrow;
+use std::fmt::{self, Debug};
+use std::iter::FromIterator;
+
+use crate::node::{find, first, insert, last, modify, size, take, AvlNode, AvlTree};
+use crate::summary::Summary;
+
+// Number of values in a subtree counting every copy, which the order
+// statistics need in place of the number of nodes
+#[derive(Debug, Clone, Copy)]
+struct Total(usize);
+
+impl<T> Summary<(T, usize)> for Total {
+    fn empty() -> Self {
+        Total(0)
+    }
+
+    fn of((_, count): &(T, usize)) -> Self {
+        Total(*count)
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        Total(self.0 + other.0)
+    }
+}
+
+type MultiSetNode<T> = AvlNode<(T, usize), Total>;
+
+fn total<T>(tree: &AvlTree<(T, usize), Total>) -> usize {
+    tree.as_ref().map_or(0, |node| node.summary.0)
+}
+
+// Sorted multiset keeping one node per distinct value along with the number
+// of copies, so duplicates cost no extra nodes
+#[derive(Clone)]
+pub struct AvlTreeMultiSet<T: Ord> {
+    root: AvlTree<(T, usize), Total>,
+}
+
+impl<T: Ord> Default for AvlTreeMultiSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord + Debug> Debug for AvlTreeMultiSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Ord> AvlTreeMultiSet<T> {
+    pub fn new() -> Self {
+        Self { root: None }
+    }
+
+    // Adds one more copy of the value and returns how many there are now
+    pub fn insert(&mut self, value: T) -> usize {
+        match insert(&mut self.root, (value, 1), &mut |a, b| a.0.cmp(&b.0)) {
+            Ok(_) => 1,
+
+            // Counting up goes through `modify` to refresh the totals above
+            Err((value, _)) => modify(
+                &mut self.root,
+                &mut |(current_value, _): &(T, usize)| current_value.cmp(&value),
+                |(_, count)| {
+                    *count += 1;
+                    *count
+                },
+            )
+            .unwrap(),
+        }
+    }
+
+    // Removes a single copy of the value, if there is one
+    pub fn remove_one<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut search = |(current_value, _): &(T, usize)| current_value.borrow().cmp(value);
+
+        match self.count(value) {
+            0 => false,
+            1 => take(&mut self.root, &mut search).is_some(),
+            _ => modify(&mut self.root, &mut search, |(_, count)| *count -= 1).is_some(),
+        }
+    }
+
+    // Removes every copy of the value and returns how many there were
+    pub fn remove_all<Q>(&mut self, value: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        take(&mut self.root, &mut |(current_value, _): &(T, usize)| {
+            current_value.borrow().cmp(value)
+        })
+        .map_or(0, |(_, count)| count)
+    }
+
+    pub fn count<Q>(&self, value: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        find(&self.root, |(current_value, _)| {
+            current_value.borrow().cmp(value)
+        })
+        .map_or(0, |(_, count)| *count)
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.count(value) > 0
+    }
+
+    // Number of values counting every copy
+    pub fn len(&self) -> usize {
+        total(&self.root)
+    }
+
+    // Number of values counting copies once
+    pub fn distinct_len(&self) -> usize {
+        size(&self.root)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    // Number of values less than the given one, counting every copy
+    pub fn rank<Q>(&self, value: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut tree = &self.root;
+        let mut rank = 0;
+
+        while let Some(current_node) = tree {
+            let (current_value, count) = &current_node.value;
+
+            if current_value.borrow() < value {
+                rank += total(&current_node.left) + count;
+                tree = &current_node.right;
+            } else {
+                tree = &current_node.left;
+            }
+        }
+
+        rank
+    }
+
+    // The value at the given position in sorted order, where every copy takes
+    // up a position of its own
+    pub fn select(&self, mut index: usize) -> Option<&T> {
+        let mut tree = &self.root;
+
+        while let Some(current_node) = tree {
+            let (current_value, count) = &current_node.value;
+            let left_total = total(&current_node.left);
+
+            if index < left_total {
+                tree = &current_node.left;
+            } else if index < left_total + count {
+                return Some(current_value);
+            } else {
+                index -= left_total + count;
+                tree = &current_node.right;
+            }
+        }
+
+        None
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        first(&self.root).map(|(value, _)| value)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        last(&self.root).map(|(value, _)| value)
+    }
+
+    // Every value as many times as it was inserted, in sorted order
+    pub fn iter(&self) -> AvlTreeMultiSetIter<'_, T> {
+        AvlTreeMultiSetIter {
+            counts: self.counts(),
+            current: None,
+            len: self.len(),
+        }
+    }
+
+    // Every distinct value along with its number of copies, in sorted order
+    pub fn counts(&self) -> AvlTreeMultiSetCounts<'_, T> {
+        let mut counts = AvlTreeMultiSetCounts {
+            prev_nodes: Vec::new(),
+            len: self.distinct_len(),
+        };
+        counts.push_left(&self.root);
+
+        counts
+    }
+}
+
+impl<T: Ord> PartialEq for AvlTreeMultiSet<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.distinct_len() == other.distinct_len() && self.counts().eq(other.counts())
+    }
+}
+
+impl<T: Ord> Eq for AvlTreeMultiSet<T> {}
+
+pub struct AvlTreeMultiSetCounts<'a, T> {
+    prev_nodes: Vec<&'a MultiSetNode<T>>,
+    len: usize,
+}
+
+impl<'a, T> Clone for AvlTreeMultiSetCounts<'a, T> {
+    fn clone(&self) -> Self {
+        Self {
+            prev_nodes: self.prev_nodes.clone(),
+            len: self.len,
+        }
+    }
+}
+
+// Iterators print the values they have left rather than the nodes they hold
+impl<'a, T: Debug> Debug for AvlTreeMultiSetCounts<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, T> AvlTreeMultiSetCounts<'a, T> {
+    fn push_left(&mut self, mut tree: &'a AvlTree<(T, usize), Total>) {
+        while let Some(current_node) = tree {
+            self.prev_nodes.push(current_node);
+            tree = &current_node.left;
+        }
+    }
+}
+
+impl<'a, T> Iterator for AvlTreeMultiSetCounts<'a, T> {
+    type Item = (&'a T, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current_node = self.prev_nodes.pop()?;
+        self.push_left(&current_node.right);
+        self.len -= 1;
+
+        let (value, count) = &current_node.value;
+
+        Some((value, *count))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for AvlTreeMultiSetCounts<'a, T> {}
+
+// Hands out the current value until its copies run out before moving on
+pub struct AvlTreeMultiSetIter<'a, T> {
+    counts: AvlTreeMultiSetCounts<'a, T>,
+    current: Option<(&'a T, usize)>,
+    len: usize,
+}
+
+impl<'a, T> Clone for AvlTreeMultiSetIter<'a, T> {
+    fn clone(&self) -> Self {
+        Self {
+            counts: self.counts.clone(),
+            current: self.current,
+            len: self.len,
+        }
+    }
+}
+
+impl<'a, T: Debug> Debug for AvlTreeMultiSetIter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, T> Iterator for AvlTreeMultiSetIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (value, remaining) = match self.current.take() {
+            Some(current) => current,
+            None => self.counts.next()?,
+        };
+
+        if remaining > 1 {
+            self.current = Some((value, remaining - 1));
+        }
+
+        self.len -= 1;
+
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for AvlTreeMultiSetIter<'a, T> {}
+
+impl<'a, T: Ord> IntoIterator for &'a AvlTreeMultiSet<T> {
+    type Item = &'a T;
+    type IntoIter = AvlTreeMultiSetIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Ord> FromIterator<T> for AvlTreeMultiSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+
+        set
+    }
+}
+
+impl<T: Ord> Extend<T> for AvlTreeMultiSet<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Rng;
+
+    #[test]
+    fn counts_copies() {
+        let mut set = AvlTreeMultiSet::new();
+
+        assert_eq!(set.insert("b"), 1);
+        assert_eq!(set.insert("a"), 1);
+        assert_eq!(set.insert("b"), 2);
+        assert_eq!(set.insert("b"), 3);
+
+        assert_eq!(set.count("b"), 3);
+        assert_eq!(set.count("c"), 0);
+        assert_eq!(set.len(), 4);
+        assert_eq!(set.distinct_len(), 2);
+
+        assert!(set.remove_one("b"));
+        assert_eq!(set.count("b"), 2);
+        assert!(set.remove_one("a"));
+        assert!(!set.remove_one("a"));
+        assert!(!set.contains("a"));
+
+        assert_eq!(set.remove_all("b"), 2);
+        assert_eq!(set.remove_all("b"), 0);
+        assert!(set.is_empty());
+        assert_eq!(set.len(), 0);
+    }
+
+    #[test]
+    fn iterates_with_and_without_repeats() {
+        let set: AvlTreeMultiSet<_> = vec![3, 1, 3, 2, 3, 1].into_iter().collect();
+
+        assert_eq!(set.iter().copied().collect::<Vec<_>>(), [1, 1, 2, 3, 3, 3]);
+        assert_eq!(set.iter().len(), 6);
+        assert_eq!(
+            set.counts().collect::<Vec<_>>(),
+            [(&1, 2), (&2, 1), (&3, 3)]
+        );
+        assert_eq!(format!("{:?}", set), "[1, 1, 2, 3, 3, 3]");
+        assert_eq!(set, vec![1, 3, 2, 3, 1, 3].into_iter().collect());
+        assert_ne!(set, vec![1, 2, 3].into_iter().collect());
+    }
+
+    #[test]
+    fn order_statistics_count_every_copy() {
+        let mut set = AvlTreeMultiSet::new();
+        let mut sorted = Vec::new();
+        let mut rng = Rng(7);
+
+        for step in 0..3000 {
+            let value = rng.below(50);
+
+            if step % 3 == 2 {
+                let position = sorted.binary_search(&value).ok();
+
+                assert_eq!(set.remove_one(&value), position.is_some());
+
+                if let Some(position) = position {
+                    sorted.remove(position);
+                }
+            } else {
+                let position = sorted.partition_point(|current| *current <= value);
+                sorted.insert(position, value);
+                set.insert(value);
+            }
+
+            assert_eq!(set.len(), sorted.len());
+            assert_eq!(
+                set.rank(&value),
+                sorted.partition_point(|current| *current < value)
+            );
+        }
+
+        for (index, value) in sorted.iter().enumerate() {
+            assert_eq!(set.select(index), Some(value));
+        }
+
+        assert_eq!(set.select(sorted.len()), None);
+        assert_eq!(set.first(), sorted.first());
+        assert_eq!(set.last(), sorted.last());
+        assert!(set.iter().eq(sorted.iter()));
+    }
+}
@@ -0,0 +1,1011 @@
+use std::cmp::{max, Ordering};
+use std::mem::{replace, swap};
+
+use crate::summary::Summary;
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct AvlNode<T, S = ()> {
+    pub(crate) value: T,
+    pub(crate) left: AvlTree<T, S>,
+    pub(crate) right: AvlTree<T, S>,
+    pub(crate) height: usize,
+    pub(crate) size: usize,
+    pub(crate) summary: S,
+}
+
+pub(crate) type AvlTree<T, S = ()> = Option<Box<AvlNode<T, S>>>;
+
+pub(crate) fn height<T, S>(tree: &AvlTree<T, S>) -> usize {
+    tree.as_ref().map_or(0, |node| node.height)
+}
+
+pub(crate) fn size<T, S>(tree: &AvlTree<T, S>) -> usize {
+    tree.as_ref().map_or(0, |node| node.size)
+}
+
+// Dismantles the tree with an explicit stack, so that dropping a deep tree
+// cannot overflow the call stack
+pub(crate) fn drop_tree<T, S>(tree: AvlTree<T, S>) {
+    let mut nodes: Vec<Box<AvlNode<T, S>>> = tree.into_iter().collect();
+
+    while let Some(mut node) = nodes.pop() {
+        nodes.extend(node.left.take());
+        nodes.extend(node.right.take());
+    }
+}
+
+// Clones the tree bottom up with an explicit stack for the same reason
+pub(crate) fn clone_tree<T: Clone, S: Clone>(tree: &AvlTree<T, S>) -> AvlTree<T, S> {
+    enum Step<'a, T, S> {
+        Visit(&'a AvlTree<T, S>),
+        Build(&'a AvlNode<T, S>),
+    }
+
+    let mut steps = vec![Step::Visit(tree)];
+    let mut cloned_trees: Vec<AvlTree<T, S>> = Vec::new();
+
+    while let Some(step) = steps.pop() {
+        match step {
+            Step::Visit(None) => cloned_trees.push(None),
+
+            // Both subtrees get cloned before the node itself is built
+            Step::Visit(Some(node)) => {
+                steps.push(Step::Build(node));
+                steps.push(Step::Visit(&node.right));
+                steps.push(Step::Visit(&node.left));
+            }
+
+            Step::Build(node) => {
+                let right = cloned_trees.pop().unwrap();
+                let left = cloned_trees.pop().unwrap();
+
+                cloned_trees.push(Some(Box::new(AvlNode {
+                    value: node.value.clone(),
+                    left,
+                    right,
+                    height: node.height,
+                    size: node.size,
+                    summary: node.summary.clone(),
+                })));
+            }
+        }
+    }
+
+    cloned_trees.pop().unwrap()
+}
+
+// Read access to a node in whichever layout a set keeps its nodes, for the
+// checks and renderings that only look at the tree
+pub(crate) trait NodeView<'a>: Copy {
+    type Value: 'a;
+
+    fn value(self) -> &'a Self::Value;
+
+    fn children(self) -> (Option<Self>, Option<Self>);
+
+    fn cached_height(self) -> usize;
+
+    fn cached_size(self) -> usize;
+}
+
+impl<'a, T, S> NodeView<'a> for &'a AvlNode<T, S> {
+    type Value = T;
+
+    fn value(self) -> &'a T {
+        &self.value
+    }
+
+    fn children(self) -> (Option<Self>, Option<Self>) {
+        (self.left.as_deref(), self.right.as_deref())
+    }
+
+    fn cached_height(self) -> usize {
+        self.height
+    }
+
+    fn cached_size(self) -> usize {
+        self.size
+    }
+}
+
+// Checks that every node keeps its subtree heights within one of each other
+// and that the cached heights and sizes are correct, without recursion
+pub(crate) fn is_balanced<'a, N: NodeView<'a>>(root: Option<N>) -> bool {
+    let mut nodes: Vec<N> = root.into_iter().collect();
+
+    while let Some(node) = nodes.pop() {
+        let (left, right) = node.children();
+        let left_height = left.map_or(0, N::cached_height);
+        let right_height = right.map_or(0, N::cached_height);
+
+        if left_height.abs_diff(right_height) > 1
+            || node.cached_height() != 1 + max(left_height, right_height)
+            || node.cached_size()
+                != 1 + left.map_or(0, N::cached_size) + right.map_or(0, N::cached_size)
+        {
+            return false;
+        }
+
+        nodes.extend(left);
+        nodes.extend(right);
+    }
+
+    true
+}
+
+// Compares values and shape of two trees, again without recursion
+pub(crate) fn structural_eq<'a, N>(tree: Option<N>, other: Option<N>) -> bool
+where
+    N: NodeView<'a>,
+    N::Value: PartialEq,
+{
+    let mut pairs = vec![(tree, other)];
+
+    while let Some(pair) = pairs.pop() {
+        match pair {
+            (None, None) => {}
+
+            (Some(node), Some(other_node)) => {
+                if node.value() != other_node.value() {
+                    return false;
+                }
+
+                let (left, right) = node.children();
+                let (other_left, other_right) = other_node.children();
+                pairs.push((left, other_left));
+                pairs.push((right, other_right));
+            }
+
+            _ => return false,
+        }
+    }
+
+    true
+}
+
+impl<T, S> AvlNode<T, S> {
+    pub(crate) fn left_height(&self) -> usize {
+        height(&self.left)
+    }
+
+    pub(crate) fn right_height(&self) -> usize {
+        height(&self.right)
+    }
+
+    pub(crate) fn left_size(&self) -> usize {
+        size(&self.left)
+    }
+
+    pub(crate) fn child_mut(&mut self, left: bool) -> &mut AvlTree<T, S> {
+        if left {
+            &mut self.left
+        } else {
+            &mut self.right
+        }
+    }
+
+    // Positive when the left subtree is taller, negative when the right one is
+    pub(crate) fn balance_factor(&self) -> isize {
+        self.left_height() as isize - self.right_height() as isize
+    }
+}
+
+impl<T, S: Summary<T>> AvlNode<T, S> {
+    pub(crate) fn new(value: T) -> Self {
+        Self {
+            left: None,
+            right: None,
+            height: 1,
+            size: 1,
+            summary: S::of(&value),
+            value,
+        }
+    }
+
+    // Recomputes the cached height, size and summary from the children
+    pub(crate) fn update(&mut self) {
+        self.height = 1 + max(self.left_height(), self.right_height());
+        self.size = 1 + self.left_size() + size(&self.right);
+
+        let mut summary = S::of(&self.value);
+
+        if let Some(left) = &self.left {
+            summary = left.summary.combine(&summary);
+        }
+
+        if let Some(right) = &self.right {
+            summary = summary.combine(&right.summary);
+        }
+
+        self.summary = summary;
+    }
+
+    // Rotations swap the boxes rather than their contents, so a value never
+    // moves in memory once it has been inserted
+    fn rotate_right(self: &mut Box<Self>) -> bool {
+        let mut left_node = match self.left.take() {
+            Some(node) => node,
+            None => return false,
+        };
+
+        // The right subtree of the left child becomes our new left subtree
+        self.left = left_node.right.take();
+        self.update();
+
+        // The left child takes our place and we become its right child
+        swap(self, &mut left_node);
+        self.right = Some(left_node);
+        self.update();
+
+        true
+    }
+
+    fn rotate_left(self: &mut Box<Self>) -> bool {
+        let mut right_node = match self.right.take() {
+            Some(node) => node,
+            None => return false,
+        };
+
+        // The left subtree of the right child becomes our new right subtree
+        self.right = right_node.left.take();
+        self.update();
+
+        // The right child takes our place and we become its left child
+        swap(self, &mut right_node);
+        self.left = Some(right_node);
+        self.update();
+
+        true
+    }
+
+    // Lifts the child on the given side into the place of the node
+    fn rotate_up(self: &mut Box<Self>, left: bool) -> bool {
+        if left {
+            self.rotate_right()
+        } else {
+            self.rotate_left()
+        }
+    }
+
+    pub(crate) fn rebalance(self: &mut Box<Self>) -> bool {
+        match self.balance_factor() {
+            2 => {
+                let left_node = self.left.as_mut().unwrap();
+
+                // Left-right case needs a double rotation
+                if left_node.balance_factor() < 0 {
+                    left_node.rotate_left();
+                }
+
+                self.rotate_right()
+            }
+
+            -2 => {
+                let right_node = self.right.as_mut().unwrap();
+
+                // Right-left case needs a double rotation
+                if right_node.balance_factor() > 0 {
+                    right_node.rotate_right();
+                }
+
+                self.rotate_left()
+            }
+
+            _ => {
+                self.update();
+
+                false
+            }
+        }
+    }
+}
+
+// Inserts the value unless an equal one is already present, in which case the
+// value is handed back
+pub(crate) fn insert<T, S: Summary<T>, F>(
+    tree: &mut AvlTree<T, S>,
+    value: T,
+    cmp: &mut F,
+) -> Result<(), T>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let current_node = match tree {
+        // Insert the value once we reach an empty tree
+        None => {
+            *tree = Some(Box::new(AvlNode::new(value)));
+
+            return Ok(());
+        }
+
+        Some(node) => node,
+    };
+
+    // Move to the left node if the value is less than the current node,
+    // right if greater, and stop if equal
+    let inserted = match cmp(&current_node.value, &value) {
+        Ordering::Less => insert(&mut current_node.right, value, cmp),
+        Ordering::Equal => return Err(value),
+        Ordering::Greater => insert(&mut current_node.left, value, cmp),
+    };
+
+    // Fix heights and rotations on the way back up
+    if inserted.is_ok() {
+        current_node.rebalance();
+    }
+
+    inserted
+}
+
+// Where a missing value goes, as found by `search`: its position in sorted
+// order and the depth of the deepest node on the way down that leans to one
+// side. Only that node can need a rotation, the nodes below it are level and
+// all grow by one.
+pub(crate) struct Vacancy {
+    index: usize,
+    critical_depth: Option<usize>,
+}
+
+// Position of the value the closure matches, like `find`, or the vacancy it
+// would fill
+pub(crate) fn search<T, S, F>(mut tree: &AvlTree<T, S>, mut f: F) -> Result<usize, Vacancy>
+where
+    F: FnMut(&T) -> Ordering,
+{
+    let mut index = 0;
+    let mut depth = 0;
+    let mut critical_depth = None;
+
+    while let Some(current_node) = tree {
+        match f(&current_node.value) {
+            Ordering::Less => {
+                index += current_node.left_size() + 1;
+                tree = &current_node.right;
+            }
+            Ordering::Equal => return Ok(index + current_node.left_size()),
+            Ordering::Greater => tree = &current_node.left,
+        }
+
+        if current_node.balance_factor() != 0 {
+            critical_depth = Some(depth);
+        }
+
+        depth += 1;
+    }
+
+    Err(Vacancy {
+        index,
+        critical_depth,
+    })
+}
+
+// Fills a vacancy of the unchanged tree in a single pass down and hands out
+// the new value. Knowing which nodes grow, their heights and sizes get fixed
+// and the rotation at the critical node gets done on the way down, so no node
+// has to be revisited. Summaries would have to be recomputed bottom up, which
+// is why this only takes plain trees.
+pub(crate) fn insert_vacant<T>(mut tree: &mut AvlTree<T>, vacancy: Vacancy, value: T) -> &mut T {
+    let Vacancy {
+        mut index,
+        critical_depth,
+    } = vacancy;
+    // Levels left down to the critical node, `None` once past it
+    let mut above = critical_depth;
+
+    while let Some(current_node) = tree {
+        let left_size = current_node.left_size();
+        let go_left = index <= left_size;
+
+        if !go_left {
+            index -= left_size + 1;
+        }
+
+        match above {
+            None => {
+                current_node.height += 1;
+                current_node.size += 1;
+            }
+
+            // The value goes to the taller side of the critical node, so the
+            // rotation that would follow the insert is done right away
+            Some(0) if (current_node.balance_factor() > 0) == go_left => {
+                above = None;
+
+                let child = current_node.child_mut(go_left).as_mut().unwrap();
+                let child_left_size = child.left_size();
+                let child_go_left = index <= child_left_size;
+
+                if !child_go_left {
+                    index -= child_left_size + 1;
+                }
+
+                // Outer grandchild, a single rotation lifts the child
+                if child_go_left == go_left {
+                    current_node.rotate_up(go_left);
+                    current_node.size += 1;
+                    tree = current_node.child_mut(go_left);
+
+                    continue;
+                }
+
+                // Inner grandchild, a double rotation lifts it between the
+                // child and the critical node. When it is the new value
+                // itself, the three nodes are small enough to just rotate.
+                let grandchild = child.child_mut(!go_left);
+
+                if grandchild.is_none() {
+                    *grandchild = Some(Box::new(AvlNode::new(value)));
+                    child.rotate_up(!go_left);
+                    current_node.rotate_up(go_left);
+
+                    return &mut current_node.value;
+                }
+
+                let grandchild_left_size = grandchild.as_ref().unwrap().left_size();
+                let grandchild_go_left = index <= grandchild_left_size;
+
+                if !grandchild_go_left {
+                    index -= grandchild_left_size + 1;
+                }
+
+                child.rotate_up(!go_left);
+                current_node.rotate_up(go_left);
+                current_node.size += 1;
+
+                // The subtree the value goes to now hangs on the inner side of
+                // one of the former parents
+                let next_node = current_node.child_mut(grandchild_go_left).as_mut().unwrap();
+                next_node.size += 1;
+                tree = next_node.child_mut(!grandchild_go_left);
+
+                continue;
+            }
+
+            // Growing the shorter side of the critical node levels it out
+            Some(levels) => {
+                above = levels.checked_sub(1);
+                current_node.size += 1;
+            }
+        }
+
+        tree = current_node.child_mut(go_left);
+    }
+
+    &mut tree.insert(Box::new(AvlNode::new(value))).value
+}
+
+// The search closures compare a node value against the looked up key, like
+// `slice::binary_search_by`
+pub(crate) fn find<T, S, F>(mut tree: &AvlTree<T, S>, mut f: F) -> Option<&T>
+where
+    F: FnMut(&T) -> Ordering,
+{
+    while let Some(current_node) = tree {
+        match f(&current_node.value) {
+            Ordering::Less => tree = &current_node.right,
+            Ordering::Equal => return Some(&current_node.value),
+            Ordering::Greater => tree = &current_node.left,
+        }
+    }
+
+    None
+}
+
+pub(crate) fn find_mut<T, S, F>(mut tree: &mut AvlTree<T, S>, mut f: F) -> Option<&mut T>
+where
+    F: FnMut(&T) -> Ordering,
+{
+    while let Some(current_node) = tree {
+        match f(&current_node.value) {
+            Ordering::Less => tree = &mut current_node.right,
+            Ordering::Equal => return Some(&mut current_node.value),
+            Ordering::Greater => tree = &mut current_node.left,
+        }
+    }
+
+    None
+}
+
+pub(crate) fn first<T, S>(mut tree: &AvlTree<T, S>) -> Option<&T> {
+    let mut first = None;
+
+    while let Some(current_node) = tree {
+        first = Some(&current_node.value);
+        tree = &current_node.left;
+    }
+
+    first
+}
+
+pub(crate) fn last<T, S>(mut tree: &AvlTree<T, S>) -> Option<&T> {
+    let mut last = None;
+
+    while let Some(current_node) = tree {
+        last = Some(&current_node.value);
+        tree = &current_node.right;
+    }
+
+    last
+}
+
+// Number of leading values for which the predicate holds, the values must be
+// partitioned like for `slice::partition_point`
+pub(crate) fn partition_point<T, S, F>(mut tree: &AvlTree<T, S>, mut pred: F) -> usize
+where
+    F: FnMut(&T) -> bool,
+{
+    let mut count = 0;
+
+    while let Some(current_node) = tree {
+        if pred(&current_node.value) {
+            count += current_node.left_size() + 1;
+            tree = &current_node.right;
+        } else {
+            tree = &current_node.left;
+        }
+    }
+
+    count
+}
+
+// Last value for which the predicate holds and first value for which it does
+// not, with the same partitioning requirement as `partition_point`
+pub(crate) fn partition_bounds<T, S, F>(
+    mut tree: &AvlTree<T, S>,
+    mut pred: F,
+) -> (Option<&T>, Option<&T>)
+where
+    F: FnMut(&T) -> bool,
+{
+    let (mut below, mut above) = (None, None);
+
+    while let Some(current_node) = tree {
+        if pred(&current_node.value) {
+            below = Some(&current_node.value);
+            tree = &current_node.right;
+        } else {
+            above = Some(&current_node.value);
+            tree = &current_node.left;
+        }
+    }
+
+    (below, above)
+}
+
+pub(crate) fn select<T, S>(mut tree: &AvlTree<T, S>, mut index: usize) -> Option<&T> {
+    while let Some(current_node) = tree {
+        let left_size = current_node.left_size();
+
+        match index.cmp(&left_size) {
+            Ordering::Less => tree = &current_node.left,
+            Ordering::Equal => return Some(&current_node.value),
+            Ordering::Greater => {
+                index -= left_size + 1;
+                tree = &current_node.right;
+            }
+        }
+    }
+
+    None
+}
+
+pub(crate) fn select_mut<T, S>(mut tree: &mut AvlTree<T, S>, mut index: usize) -> Option<&mut T> {
+    while let Some(current_node) = tree {
+        let left_size = current_node.left_size();
+
+        match index.cmp(&left_size) {
+            Ordering::Less => tree = &mut current_node.left,
+            Ordering::Equal => return Some(&mut current_node.value),
+            Ordering::Greater => {
+                index -= left_size + 1;
+                tree = &mut current_node.right;
+            }
+        }
+    }
+
+    None
+}
+
+// Summary of the values that are after the lower bound and before the upper
+// one. Like for `partition_point`, `after_lower` must fail for a prefix of
+// the values and `before_upper` hold for a prefix, a missing predicate holds
+// everywhere. Only the paths to both bounds get walked, the subtrees hanging
+// between them contribute their cached summaries, so this takes O(log n).
+pub(crate) fn fold<T, S, L, U>(
+    tree: &AvlTree<T, S>,
+    mut after_lower: Option<&mut L>,
+    mut before_upper: Option<&mut U>,
+) -> S
+where
+    S: Summary<T>,
+    L: FnMut(&T) -> bool,
+    U: FnMut(&T) -> bool,
+{
+    let current_node = match tree {
+        None => return S::empty(),
+        Some(node) => node,
+    };
+
+    if after_lower.is_none() && before_upper.is_none() {
+        return current_node.summary.clone();
+    }
+
+    // The whole left subtree is below the range, or the right one above it
+    if after_lower
+        .as_mut()
+        .is_some_and(|f| !f(&current_node.value))
+    {
+        return fold(&current_node.right, after_lower, before_upper);
+    }
+
+    if before_upper
+        .as_mut()
+        .is_some_and(|f| !f(&current_node.value))
+    {
+        return fold(&current_node.left, after_lower, before_upper);
+    }
+
+    // The range contains the node, so it covers the rest of the left subtree
+    // above the lower bound and the rest of the right one below the upper bound
+    let left = fold(&current_node.left, after_lower, None::<&mut U>);
+    let right = fold(&current_node.right, None::<&mut L>, before_upper);
+
+    left.combine(&S::of(&current_node.value)).combine(&right)
+}
+
+// Applies the function to the searched value in place and refreshes the
+// cached summaries on the path back up, since they may depend on the value.
+// The function must not change how the value compares to the others.
+pub(crate) fn modify<T, S, F, G, R>(tree: &mut AvlTree<T, S>, f: &mut F, g: G) -> Option<R>
+where
+    S: Summary<T>,
+    F: FnMut(&T) -> Ordering,
+    G: FnOnce(&mut T) -> R,
+{
+    let current_node = tree.as_mut()?;
+
+    let modified = match f(&current_node.value) {
+        Ordering::Less => modify(&mut current_node.right, f, g),
+        Ordering::Equal => Some(g(&mut current_node.value)),
+        Ordering::Greater => modify(&mut current_node.left, f, g),
+    };
+
+    if modified.is_some() {
+        current_node.update();
+    }
+
+    modified
+}
+
+pub(crate) fn take<T, S: Summary<T>, F>(tree: &mut AvlTree<T, S>, f: &mut F) -> Option<T>
+where
+    F: FnMut(&T) -> Ordering,
+{
+    let ordering = f(&tree.as_ref()?.value);
+
+    // Remove the current node itself when we hit the value
+    if ordering == Ordering::Equal {
+        return Some(take_root(tree));
+    }
+
+    let current_node = tree.as_mut().unwrap();
+
+    let taken = match ordering {
+        Ordering::Less => take(&mut current_node.right, f),
+        _ => take(&mut current_node.left, f),
+    };
+
+    // Fix heights and rotations on the way back up
+    if taken.is_some() {
+        current_node.rebalance();
+    }
+
+    taken
+}
+
+pub(crate) fn take_at<T, S: Summary<T>>(tree: &mut AvlTree<T, S>, index: usize) -> Option<T> {
+    let left_size = tree.as_ref()?.left_size();
+
+    // Remove the current node itself when we hit the position
+    if index == left_size {
+        return Some(take_root(tree));
+    }
+
+    let current_node = tree.as_mut().unwrap();
+
+    let taken = if index < left_size {
+        take_at(&mut current_node.left, index)
+    } else {
+        take_at(&mut current_node.right, index - left_size - 1)
+    };
+
+    // Fix heights and rotations on the way back up
+    if taken.is_some() {
+        current_node.rebalance();
+    }
+
+    taken
+}
+
+fn take_root<T, S: Summary<T>>(tree: &mut AvlTree<T, S>) -> T {
+    let mut node = tree.take().unwrap();
+
+    match (node.left.take(), node.right.take()) {
+        (None, None) => {}
+        (Some(child), None) | (None, Some(child)) => *tree = Some(child),
+
+        // Two children: the in-order successor takes the place of the removed value
+        (left, mut right) => {
+            let successor = pop_first(&mut right).unwrap();
+            let value = replace(&mut node.value, successor);
+
+            node.left = left;
+            node.right = right;
+            node.rebalance();
+            *tree = Some(node);
+
+            return value;
+        }
+    }
+
+    node.value
+}
+
+pub(crate) fn pop_first<T, S: Summary<T>>(tree: &mut AvlTree<T, S>) -> Option<T> {
+    let current_node = tree.as_mut()?;
+
+    if current_node.left.is_none() {
+        return Some(take_root(tree));
+    }
+
+    let value = pop_first(&mut current_node.left);
+    current_node.rebalance();
+
+    value
+}
+
+pub(crate) fn pop_last<T, S: Summary<T>>(tree: &mut AvlTree<T, S>) -> Option<T> {
+    let current_node = tree.as_mut()?;
+
+    if current_node.right.is_none() {
+        return Some(take_root(tree));
+    }
+
+    let value = pop_last(&mut current_node.right);
+    current_node.rebalance();
+
+    value
+}
+
+// Joins two trees with a pivot that lies between all values of the left tree
+// and all values of the right one. Runs in O(|height(left) - height(right)|).
+pub(crate) fn join<T, S: Summary<T>>(
+    left: AvlTree<T, S>,
+    pivot: T,
+    right: AvlTree<T, S>,
+) -> Box<AvlNode<T, S>> {
+    let (left_height, right_height) = (height(&left), height(&right));
+
+    if left_height > right_height + 1 {
+        join_right(left.unwrap(), pivot, right)
+    } else if right_height > left_height + 1 {
+        join_left(left, pivot, right.unwrap())
+    } else {
+        let mut node = Box::new(AvlNode::new(pivot));
+        node.left = left;
+        node.right = right;
+        node.update();
+
+        node
+    }
+}
+
+// Walks down the right spine of the taller left tree until the heights match
+fn join_right<T, S: Summary<T>>(
+    mut left: Box<AvlNode<T, S>>,
+    pivot: T,
+    right: AvlTree<T, S>,
+) -> Box<AvlNode<T, S>> {
+    let inner_tree = left.right.take();
+
+    left.right = Some(if height(&inner_tree) <= height(&right) + 1 {
+        join(inner_tree, pivot, right)
+    } else {
+        join_right(inner_tree.unwrap(), pivot, right)
+    });
+    left.rebalance();
+
+    left
+}
+
+// Walks down the left spine of the taller right tree until the heights match
+fn join_left<T, S: Summary<T>>(
+    left: AvlTree<T, S>,
+    pivot: T,
+    mut right: Box<AvlNode<T, S>>,
+) -> Box<AvlNode<T, S>> {
+    let inner_tree = right.left.take();
+
+    right.left = Some(if height(&inner_tree) <= height(&left) + 1 {
+        join(left, pivot, inner_tree)
+    } else {
+        join_left(left, pivot, inner_tree.unwrap())
+    });
+    right.rebalance();
+
+    right
+}
+
+// Joins two trees where all values of the left tree are less than all values
+// of the right one
+pub(crate) fn join_trees<T, S: Summary<T>>(
+    left: AvlTree<T, S>,
+    mut right: AvlTree<T, S>,
+) -> AvlTree<T, S> {
+    match pop_first(&mut right) {
+        None => left,
+        Some(pivot) => Some(join(left, pivot, right)),
+    }
+}
+
+// Builds a perfectly balanced tree out of the next len values in O(len). The
+// halves differ in size by at most one, so the recursion is only O(log len)
+// deep and every node ends up with a balance factor of zero or one.
+pub(crate) fn build_sorted<T, S: Summary<T>, I>(values: &mut I, len: usize) -> AvlTree<T, S>
+where
+    I: Iterator<Item = T>,
+{
+    if len == 0 {
+        return None;
+    }
+
+    let left = build_sorted(values, len / 2);
+    let mut node = Box::new(AvlNode::new(values.next()?));
+    node.left = left;
+    node.right = build_sorted(values, len - len / 2 - 1);
+    node.update();
+
+    Some(node)
+}
+
+// Splits the tree into the values before the searched one, the value itself
+// if present, and the values after it
+pub(crate) fn split<T, S: Summary<T>, F>(
+    tree: AvlTree<T, S>,
+    f: &mut F,
+) -> (AvlTree<T, S>, Option<T>, AvlTree<T, S>)
+where
+    F: FnMut(&T) -> Ordering,
+{
+    let AvlNode {
+        value, left, right, ..
+    } = match tree {
+        None => return (None, None, None),
+        Some(node) => *node,
+    };
+
+    match f(&value) {
+        Ordering::Less => {
+            let (right_left, found, right_right) = split(right, f);
+
+            (Some(join(left, value, right_left)), found, right_right)
+        }
+
+        Ordering::Equal => (left, Some(value), right),
+
+        Ordering::Greater => {
+            let (left_left, found, left_right) = split(left, f);
+
+            (left_left, found, Some(join(left_right, value, right)))
+        }
+    }
+}
+
+// The set operations below split one tree by the root of the other and recurse
+// on both halves, which takes O(m log(n/m + 1)) for trees of sizes m <= n
+
+pub(crate) fn union<T, S: Summary<T>, C>(
+    tree: AvlTree<T, S>,
+    other: AvlTree<T, S>,
+    cmp: &mut C,
+) -> AvlTree<T, S>
+where
+    C: FnMut(&T, &T) -> Ordering,
+{
+    let (node, other) = match (tree, other) {
+        (None, other) => return other,
+        (tree, None) => return tree,
+        (Some(node), other) => (node, other),
+    };
+    let AvlNode {
+        value, left, right, ..
+    } = *node;
+
+    // Values of the first tree win over equal values of the other one
+    let (other_left, _, other_right) = split(other, &mut |other_value| cmp(other_value, &value));
+    let left = union(left, other_left, cmp);
+    let right = union(right, other_right, cmp);
+
+    Some(join(left, value, right))
+}
+
+pub(crate) fn intersection<T, S: Summary<T>, C>(
+    tree: AvlTree<T, S>,
+    other: AvlTree<T, S>,
+    cmp: &mut C,
+) -> AvlTree<T, S>
+where
+    C: FnMut(&T, &T) -> Ordering,
+{
+    let (node, other) = match (tree, other) {
+        (Some(node), Some(other)) => (node, Some(other)),
+        _ => return None,
+    };
+    let AvlNode {
+        value, left, right, ..
+    } = *node;
+
+    let (other_left, found, other_right) =
+        split(other, &mut |other_value| cmp(other_value, &value));
+    let left = intersection(left, other_left, cmp);
+    let right = intersection(right, other_right, cmp);
+
+    match found {
+        Some(_) => Some(join(left, value, right)),
+        None => join_trees(left, right),
+    }
+}
+
+pub(crate) fn difference<T, S: Summary<T>, C>(
+    tree: AvlTree<T, S>,
+    other: AvlTree<T, S>,
+    cmp: &mut C,
+) -> AvlTree<T, S>
+where
+    C: FnMut(&T, &T) -> Ordering,
+{
+    let (tree, other_node) = match (tree, other) {
+        (None, _) => return None,
+        (tree, None) => return tree,
+        (tree, Some(other_node)) => (tree, other_node),
+    };
+    let AvlNode {
+        value, left, right, ..
+    } = *other_node;
+
+    // Split the first tree by the removed value and drop it if present
+    let (tree_left, _, tree_right) = split(tree, &mut |tree_value| cmp(tree_value, &value));
+    let left = difference(tree_left, left, cmp);
+    let right = difference(tree_right, right, cmp);
+
+    join_trees(left, right)
+}
+
+pub(crate) fn symmetric_difference<T, S: Summary<T>, C>(
+    tree: AvlTree<T, S>,
+    other: AvlTree<T, S>,
+    cmp: &mut C,
+) -> AvlTree<T, S>
+where
+    C: FnMut(&T, &T) -> Ordering,
+{
+    let (node, other) = match (tree, other) {
+        (None, other) => return other,
+        (tree, None) => return tree,
+        (Some(node), other) => (node, other),
+    };
+    let AvlNode {
+        value, left, right, ..
+    } = *node;
+
+    let (other_left, found, other_right) =
+        split(other, &mut |other_value| cmp(other_value, &value));
+    let left = symmetric_difference(left, other_left, cmp);
+    let right = symmetric_difference(right, other_right, cmp);
+
+    // Values present in both trees are dropped
+    match found {
+        Some(_) => join_trees(left, right),
+        None => Some(join(left, value, right)),
+    }
+}
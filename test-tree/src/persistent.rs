@@ -0,0 +1,592 @@
+use std::borrow::Borrow;
+use std::cmp::{max, Ordering};
+use std::fmt::{self, Debug};
+use std::iter::FromIterator;
+use std::sync::Arc;
+
+use crate::compare::{Compare, Natural};
+use crate::node::{is_balanced, NodeView};
+
+// Variant of `AvlTreeSet` whose nodes are shared between versions. Nodes are
+// never changed once built: an insert or remove copies only the nodes on the
+// path to the changed value and links the new copies to the untouched
+// subtrees of the old version, so each update allocates O(log n) nodes and
+// every older version stays valid as a snapshot.
+
+#[derive(Debug)]
+struct PersistentNode<T> {
+    value: T,
+    left: Link<T>,
+    right: Link<T>,
+    height: usize,
+    size: usize,
+}
+
+type Link<T> = Option<Arc<PersistentNode<T>>>;
+
+impl<'a, T> NodeView<'a> for &'a PersistentNode<T> {
+    type Value = T;
+
+    fn value(self) -> &'a T {
+        &self.value
+    }
+
+    fn children(self) -> (Option<Self>, Option<Self>) {
+        (self.left.as_deref(), self.right.as_deref())
+    }
+
+    fn cached_height(self) -> usize {
+        self.height
+    }
+
+    fn cached_size(self) -> usize {
+        self.size
+    }
+}
+
+fn height<T>(tree: &Link<T>) -> usize {
+    tree.as_ref().map_or(0, |node| node.height)
+}
+
+fn size<T>(tree: &Link<T>) -> usize {
+    tree.as_ref().map_or(0, |node| node.size)
+}
+
+fn node<T>(left: Link<T>, value: T, right: Link<T>) -> Link<T> {
+    Some(Arc::new(PersistentNode {
+        height: 1 + max(height(&left), height(&right)),
+        size: 1 + size(&left) + size(&right),
+        value,
+        left,
+        right,
+    }))
+}
+
+// Builds a node out of subtrees whose heights differ by at most two, rotating
+// copies of the nodes on the taller side when they differ by two
+fn balance<T: Clone>(left: Link<T>, value: T, right: Link<T>) -> Link<T> {
+    let (left_height, right_height) = (height(&left), height(&right));
+
+    if left_height > right_height + 1 {
+        let left = left.unwrap();
+
+        if height(&left.left) >= height(&left.right) {
+            node(
+                left.left.clone(),
+                left.value.clone(),
+                node(left.right.clone(), value, right),
+            )
+        } else {
+            let inner = left.right.as_ref().unwrap();
+
+            node(
+                node(left.left.clone(), left.value.clone(), inner.left.clone()),
+                inner.value.clone(),
+                node(inner.right.clone(), value, right),
+            )
+        }
+    } else if right_height > left_height + 1 {
+        let right = right.unwrap();
+
+        if height(&right.right) >= height(&right.left) {
+            node(
+                node(left, value, right.left.clone()),
+                right.value.clone(),
+                right.right.clone(),
+            )
+        } else {
+            let inner = right.left.as_ref().unwrap();
+
+            node(
+                node(left, value, inner.left.clone()),
+                inner.value.clone(),
+                node(
+                    inner.right.clone(),
+                    right.value.clone(),
+                    right.right.clone(),
+                ),
+            )
+        }
+    } else {
+        node(left, value, right)
+    }
+}
+
+// Returns the new version of the subtree, or None if an equal value is
+// already present and nothing had to be copied
+fn insert<T: Clone, F>(tree: &Link<T>, value: T, cmp: &mut F) -> Option<Link<T>>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let current_node = match tree {
+        None => return Some(node(None, value, None)),
+        Some(current_node) => current_node,
+    };
+
+    match cmp(&value, &current_node.value) {
+        Ordering::Less => Some(balance(
+            insert(&current_node.left, value, cmp)?,
+            current_node.value.clone(),
+            current_node.right.clone(),
+        )),
+        Ordering::Greater => Some(balance(
+            current_node.left.clone(),
+            current_node.value.clone(),
+            insert(&current_node.right, value, cmp)?,
+        )),
+        Ordering::Equal => None,
+    }
+}
+
+// Returns the new version of the subtree without the least value, and a
+// reference to that value in the old version
+fn remove_first<T: Clone>(current_node: &PersistentNode<T>) -> (Link<T>, &T) {
+    match &current_node.left {
+        None => (current_node.right.clone(), &current_node.value),
+        Some(left) => {
+            let (left, first) = remove_first(left);
+
+            (
+                balance(left, current_node.value.clone(), current_node.right.clone()),
+                first,
+            )
+        }
+    }
+}
+
+// Returns the new version of the subtree, or None if no value matched
+fn remove<T: Clone, F>(tree: &Link<T>, f: &mut F) -> Option<Link<T>>
+where
+    F: FnMut(&T) -> Ordering,
+{
+    let current_node = tree.as_ref()?;
+
+    match f(&current_node.value) {
+        Ordering::Greater => Some(balance(
+            remove(&current_node.left, f)?,
+            current_node.value.clone(),
+            current_node.right.clone(),
+        )),
+        Ordering::Less => Some(balance(
+            current_node.left.clone(),
+            current_node.value.clone(),
+            remove(&current_node.right, f)?,
+        )),
+        Ordering::Equal => Some(match (&current_node.left, &current_node.right) {
+            (None, right) => right.clone(),
+            (left, None) => left.clone(),
+            (left, Some(right)) => {
+                let (right, successor) = remove_first(right);
+
+                balance(left.clone(), successor.clone(), right)
+            }
+        }),
+    }
+}
+
+// Cloning shares the root and the comparator, so it is O(1) and the clone is
+// a snapshot that later updates of the original do not affect
+pub struct PersistentAvlTreeSet<T, C = Natural> {
+    root: Link<T>,
+    cmp: Arc<C>,
+}
+
+impl<T, C> Clone for PersistentAvlTreeSet<T, C> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            cmp: self.cmp.clone(),
+        }
+    }
+}
+
+impl<T: Debug, C: Compare<T>> Debug for PersistentAvlTreeSet<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T, C: Compare<T> + Default> Default for PersistentAvlTreeSet<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T: Ord> PersistentAvlTreeSet<T> {
+    pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+}
+
+impl<T, C: Compare<T>> PersistentAvlTreeSet<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        Self {
+            root: None,
+            cmp: Arc::new(cmp),
+        }
+    }
+
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
+    fn with_root(&self, root: Link<T>) -> Self {
+        Self {
+            root,
+            cmp: self.cmp.clone(),
+        }
+    }
+
+    // Returns a new version with the value added, or a clone of this one if
+    // an equal value is already present
+    pub fn insert(&self, value: T) -> Self
+    where
+        T: Clone,
+    {
+        let cmp = &*self.cmp;
+
+        match insert(&self.root, value, &mut |a: &T, b: &T| cmp.compare(a, b)) {
+            Some(root) => self.with_root(root),
+            None => self.clone(),
+        }
+    }
+
+    // Returns a new version without the value, or a clone of this one if the
+    // value is not present
+    pub fn remove<Q>(&self, value: &Q) -> Self
+    where
+        T: Borrow<Q> + Clone,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let cmp = &*self.cmp;
+
+        match remove(&self.root, &mut |current_value: &T| {
+            cmp.compare(current_value.borrow(), value)
+        }) {
+            Some(root) => self.with_root(root),
+            None => self.clone(),
+        }
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.get(value).is_some()
+    }
+
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let mut current_tree = &self.root;
+
+        while let Some(current_node) = current_tree {
+            current_tree = match self.cmp.compare(current_node.value.borrow(), value) {
+                Ordering::Greater => &current_node.left,
+                Ordering::Less => &current_node.right,
+                Ordering::Equal => return Some(&current_node.value),
+            };
+        }
+
+        None
+    }
+
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.iter().next()
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.iter().next_back()
+    }
+
+    pub fn height(&self) -> usize {
+        height(&self.root)
+    }
+
+    // Whether both versions are the same tree, which is what an update that
+    // did not change anything returns
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.root, &other.root) {
+            (Some(root), Some(other_root)) => Arc::ptr_eq(root, other_root),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    // Checks that every node keeps its subtree heights within one of each other
+    // and that the cached heights and sizes are correct
+    pub fn is_balanced(&self) -> bool {
+        is_balanced(self.root.as_deref())
+    }
+
+    pub fn iter(&self) -> PersistentAvlTreeSetIter<'_, T> {
+        let mut iter = PersistentAvlTreeSetIter {
+            front_nodes: Vec::new(),
+            back_nodes: Vec::new(),
+            len: self.len(),
+        };
+        iter.push_left(&self.root);
+        iter.push_right(&self.root);
+
+        iter
+    }
+}
+
+// Versions sharing their tree are equal without walking it
+impl_value_order!(PersistentAvlTreeSet, Self::ptr_eq);
+
+// Both ends keep a stack of the nodes whose subtree on the inner side has not
+// been visited yet, the remaining length tells when the ends meet
+pub struct PersistentAvlTreeSetIter<'a, T> {
+    front_nodes: Vec<&'a PersistentNode<T>>,
+    back_nodes: Vec<&'a PersistentNode<T>>,
+    len: usize,
+}
+
+impl<'a, T> Clone for PersistentAvlTreeSetIter<'a, T> {
+    fn clone(&self) -> Self {
+        Self {
+            front_nodes: self.front_nodes.clone(),
+            back_nodes: self.back_nodes.clone(),
+            len: self.len,
+        }
+    }
+}
+
+// Iterators print the values they have left rather than the nodes they hold
+impl<'a, T: Debug> Debug for PersistentAvlTreeSetIter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, T> PersistentAvlTreeSetIter<'a, T> {
+    fn push_left(&mut self, mut current_tree: &'a Link<T>) {
+        while let Some(current_node) = current_tree {
+            self.front_nodes.push(current_node);
+            current_tree = &current_node.left;
+        }
+    }
+
+    fn push_right(&mut self, mut current_tree: &'a Link<T>) {
+        while let Some(current_node) = current_tree {
+            self.back_nodes.push(current_node);
+            current_tree = &current_node.right;
+        }
+    }
+}
+
+impl<'a, T> Iterator for PersistentAvlTreeSetIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let current_node = self.front_nodes.pop()?;
+        self.push_left(&current_node.right);
+        self.len -= 1;
+
+        Some(&current_node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for PersistentAvlTreeSetIter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let current_node = self.back_nodes.pop()?;
+        self.push_right(&current_node.left);
+        self.len -= 1;
+
+        Some(&current_node.value)
+    }
+}
+
+impl<'a, T> ExactSizeIterator for PersistentAvlTreeSetIter<'a, T> {}
+
+impl<'a, T, C: Compare<T>> IntoIterator for &'a PersistentAvlTreeSet<T, C> {
+    type Item = &'a T;
+    type IntoIter = PersistentAvlTreeSetIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Clone, C: Compare<T> + Default> FromIterator<T> for PersistentAvlTreeSet<T, C> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::default();
+        set.extend(iter);
+
+        set
+    }
+}
+
+// Extending replaces this version with the new one, other snapshots keep
+// seeing the values they had
+impl<T: Clone, C: Compare<T>> Extend<T> for PersistentAvlTreeSet<T, C> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            *self = self.insert(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Rng;
+    use std::collections::{BTreeSet, HashSet};
+    use std::thread;
+
+    // Addresses of all nodes of a version
+    fn nodes<T>(set: &PersistentAvlTreeSet<T>) -> HashSet<*const PersistentNode<T>> {
+        let mut nodes = HashSet::new();
+        let mut trees = vec![&set.root];
+
+        while let Some(Some(node)) = trees.pop() {
+            nodes.insert(&**node as *const _);
+            trees.push(&node.left);
+            trees.push(&node.right);
+        }
+
+        nodes
+    }
+
+    #[test]
+    fn versions_stay_unchanged() {
+        let mut versions = vec![PersistentAvlTreeSet::new()];
+
+        for i in 0..200 {
+            let next = versions.last().unwrap().insert(i);
+            versions.push(next);
+        }
+
+        for i in (0..200).step_by(3) {
+            let next = versions.last().unwrap().remove(&i);
+            versions.push(next);
+        }
+
+        for (index, version) in versions.iter().enumerate().take(201) {
+            assert!(version.is_balanced());
+            assert!(version.iter().copied().eq(0..index));
+        }
+
+        let last = versions.last().unwrap();
+        assert!(last.is_balanced());
+        assert!(last.iter().copied().eq((0..200).filter(|i| i % 3 != 0)));
+    }
+
+    #[test]
+    fn updates_copy_only_the_path() {
+        let set: PersistentAvlTreeSet<_> = (0..1000).map(|i| i * 2).collect();
+        let old_nodes = nodes(&set);
+
+        let inserted = set.insert(501);
+        let copied = nodes(&inserted).difference(&old_nodes).count();
+        assert!(copied <= set.height() + 2, "{} nodes were copied", copied);
+
+        let removed = set.remove(&500);
+        let copied = nodes(&removed).difference(&old_nodes).count();
+        assert!(copied <= set.height() + 2, "{} nodes were copied", copied);
+
+        // Updates that change nothing share the whole tree
+        assert!(set.insert(500).ptr_eq(&set));
+        assert!(set.remove(&501).ptr_eq(&set));
+        assert!(set.clone().ptr_eq(&set));
+    }
+
+    #[test]
+    fn shared_versions_are_equal_without_comparing_values() {
+        // NaN is never equal to itself, so only the shared tree makes these equal
+        let by_bits = |a: &f64, b: &f64| a.total_cmp(b);
+        let set = PersistentAvlTreeSet::with_comparator(by_bits).insert(f64::NAN);
+        let rebuilt = PersistentAvlTreeSet::with_comparator(by_bits).insert(f64::NAN);
+
+        assert!(set == set.clone());
+        assert!(set == set.insert(f64::NAN));
+        assert!(set != rebuilt);
+    }
+
+    #[test]
+    fn matches_btree_set() {
+        let mut set = PersistentAvlTreeSet::new();
+        let mut expected = BTreeSet::new();
+        let mut rng = Rng(7);
+
+        for step in 0..3000 {
+            let key = rng.below(400);
+
+            if step % 3 == 0 {
+                set = set.remove(&key);
+                expected.remove(&key);
+            } else {
+                set = set.insert(key);
+                expected.insert(key);
+            }
+
+            assert!(set.is_balanced());
+            assert_eq!(set.len(), expected.len());
+            assert_eq!(set.contains(&key), expected.contains(&key));
+        }
+
+        assert!(set.iter().eq(expected.iter()));
+        assert!(set.iter().rev().eq(expected.iter().rev()));
+        assert_eq!(set.first(), expected.iter().next());
+        assert_eq!(set.last(), expected.iter().next_back());
+    }
+
+    #[test]
+    fn snapshots_are_shared_with_readers() {
+        let mut set = PersistentAvlTreeSet::new();
+        let mut readers = Vec::new();
+
+        for i in 0..8 {
+            set.extend(i * 100..(i + 1) * 100);
+
+            let snapshot = set.clone();
+            readers.push(thread::spawn(move || {
+                assert!(snapshot.is_balanced());
+                assert!(snapshot.iter().copied().eq(0..(i + 1) * 100));
+            }));
+        }
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn custom_comparator() {
+        let set = PersistentAvlTreeSet::with_comparator(|a: &String, b: &String| {
+            a.to_lowercase().cmp(&b.to_lowercase())
+        });
+
+        let set = set.insert("b".to_string()).insert("A".to_string());
+        assert!(set.insert("B".to_string()).ptr_eq(&set));
+        assert!(set.contains(&"a".to_string()));
+        assert!(set.iter().eq(&["A".to_string(), "b".to_string()]));
+    }
+}
@@ -0,0 +1,1313 @@
+use std::borrow::Borrow;
+use std::cmp::{max, Ordering};
+use std::fmt::{self, Debug};
+use std::iter::FromIterator;
+use std::ops::RangeBounds;
+
+use crate::compare::{Compare, Natural};
+use crate::node::{
+    clone_tree, drop_tree, find, first, height, insert, is_balanced, join, join_trees, last,
+    partition_bounds, partition_point, pop_first, pop_last, select, size, split, structural_eq,
+    take, union, AvlNode, AvlTree,
+};
+
+mod bulk;
+mod cursor;
+mod extract;
+mod ops;
+mod par;
+mod render;
+#[cfg(debug_assertions)]
+mod validate;
+
+pub use bulk::{SortedInput, UnsortedError};
+pub use cursor::{Cursor, CursorMut};
+pub use extract::ExtractIf;
+pub use ops::{Difference, Intersection, SymmetricDifference, Union};
+pub use par::AvlTreeSetParIter;
+#[cfg(debug_assertions)]
+pub use validate::{InvariantError, Violation};
+
+pub(crate) use bulk::check_sorted;
+pub(crate) use render::{to_dot, to_pretty_string};
+#[cfg(debug_assertions)]
+pub(crate) use validate::validate_tree;
+
+// Values are ordered by the comparator, which defaults to their natural order
+pub struct AvlTreeSet<T, C = Natural> {
+    root: AvlTree<T>,
+    cmp: C,
+}
+
+// Drop, Clone and Debug work without recursion, so they cannot overflow the
+// stack no matter how deep the tree is
+
+impl<T, C> Drop for AvlTreeSet<T, C> {
+    fn drop(&mut self) {
+        drop_tree(self.root.take());
+    }
+}
+
+impl<T: Clone, C: Clone> Clone for AvlTreeSet<T, C> {
+    fn clone(&self) -> Self {
+        Self {
+            root: clone_tree(&self.root),
+            cmp: self.cmp.clone(),
+        }
+    }
+}
+
+impl<T: Debug, C> Debug for AvlTreeSet<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let iter = AvlTreeSetIter::new(&self.root, 0, size(&self.root));
+
+        f.debug_set().entries(iter).finish()
+    }
+}
+
+impl<T, C: Compare<T> + Default> Default for AvlTreeSet<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T: Ord> AvlTreeSet<T> {
+    pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+}
+
+impl<T, C: Compare<T>> AvlTreeSet<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        Self { root: None, cmp }
+    }
+
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
+    pub fn insert(&mut self, value: T) -> bool {
+        let cmp = &self.cmp;
+
+        insert(&mut self.root, value, &mut |a: &T, b: &T| cmp.compare(a, b)).is_ok()
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.get(value).is_some()
+    }
+
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        find(&self.root, |current_value| {
+            self.cmp.compare(current_value.borrow(), value)
+        })
+    }
+
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        self.take(value).is_some()
+    }
+
+    pub fn take<Q>(&mut self, value: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let cmp = &self.cmp;
+
+        take(&mut self.root, &mut |current_value: &T| {
+            cmp.compare(current_value.borrow(), value)
+        })
+    }
+
+    pub fn pop_first(&mut self) -> Option<T> {
+        pop_first(&mut self.root)
+    }
+
+    pub fn pop_last(&mut self) -> Option<T> {
+        pop_last(&mut self.root)
+    }
+
+    // Moves all values greater than or equal to the given one into a new set
+    pub fn split_off<Q>(&mut self, value: &Q) -> Self
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q> + Clone,
+    {
+        let cmp = &self.cmp;
+        let (left, found, right) = split(self.root.take(), &mut |current_value: &T| {
+            cmp.compare(current_value.borrow(), value)
+        });
+
+        self.root = left;
+
+        Self {
+            root: match found {
+                Some(found) => Some(join(None, found, right)),
+                None => right,
+            },
+            cmp: self.cmp.clone(),
+        }
+    }
+
+    // Moves all values of the other set into this one. Sets that do not overlap
+    // are joined in O(log n), overlapping ones fall back to a union.
+    pub fn append(&mut self, other: &mut Self) {
+        let (tree, other_tree) = (self.root.take(), other.root.take());
+        let cmp = &self.cmp;
+
+        let is_before = match (last(&tree), first(&other_tree)) {
+            (Some(last_value), Some(first_value)) => {
+                cmp.compare(last_value, first_value) == Ordering::Less
+            }
+            _ => true,
+        };
+        let is_after = match (first(&tree), last(&other_tree)) {
+            (Some(first_value), Some(last_value)) => {
+                cmp.compare(first_value, last_value) == Ordering::Greater
+            }
+            _ => true,
+        };
+
+        self.root = if is_before {
+            join_trees(tree, other_tree)
+        } else if is_after {
+            join_trees(other_tree, tree)
+        } else {
+            union(tree, other_tree, &mut |a: &T, b: &T| cmp.compare(a, b))
+        };
+    }
+
+    // Builds a set out of a pivot and two sets with only smaller and only
+    // greater values in O(|left.height() - right.height()|). The joined set
+    // keeps the comparator of the left one.
+    pub fn join(mut left: Self, pivot: T, mut right: Self) -> Self {
+        assert!(
+            last(&left.root).is_none_or(|value| left.cmp.compare(value, &pivot) == Ordering::Less)
+                && first(&right.root)
+                    .is_none_or(|value| left.cmp.compare(value, &pivot) == Ordering::Greater),
+            "joined sets must be ordered around the pivot in AvlTreeSet"
+        );
+
+        left.root = Some(join(left.root.take(), pivot, right.root.take()));
+
+        left
+    }
+
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    // The value at the given position in sorted order
+    pub fn select(&self, index: usize) -> Option<&T> {
+        select(&self.root, index)
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        first(&self.root)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        last(&self.root)
+    }
+
+    pub fn height(&self) -> usize {
+        height(&self.root)
+    }
+
+    // Equality compares the values only, this also compares the shape of the
+    // trees, which depends on the order the values were inserted in
+    pub fn structural_eq(&self, other: &Self) -> bool
+    where
+        T: PartialEq,
+    {
+        structural_eq(self.root.as_deref(), other.root.as_deref())
+    }
+
+    // Checks that every node keeps its subtree heights within one of each other
+    // and that the cached heights and sizes are correct. `validate` checks the
+    // same and the ordering on top, and tells which node is broken, but it is
+    // only compiled into debug builds. This check stays available in release
+    // builds and is the one every set shares.
+    pub fn is_balanced(&self) -> bool {
+        is_balanced(self.root.as_deref())
+    }
+}
+
+impl_sorted_lookups!(AvlTreeSet);
+impl_value_order!(AvlTreeSet);
+
+impl<T, C> AvlTreeSet<T, C> {
+    fn partition_point<F: FnMut(&T) -> bool>(&self, pred: F) -> usize {
+        partition_point(&self.root, pred)
+    }
+
+    fn partition_bounds<F: FnMut(&T) -> bool>(&self, pred: F) -> (Option<&T>, Option<&T>) {
+        partition_bounds(&self.root, pred)
+    }
+}
+
+// Both ends keep a stack of the nodes whose subtree on the inner side has not
+// been visited yet, together with their position in the whole tree. Knowing the
+// positions lets the iterator seek from the root instead of walking.
+pub struct AvlTreeSetIter<'a, T> {
+    root: &'a AvlTree<T>,
+    front_nodes: Vec<&'a AvlNode<T>>,
+    back_nodes: Vec<&'a AvlNode<T>>,
+    front: usize,
+    back: usize,
+}
+
+impl<'a, T> Clone for AvlTreeSetIter<'a, T> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root,
+            front_nodes: self.front_nodes.clone(),
+            back_nodes: self.back_nodes.clone(),
+            front: self.front,
+            back: self.back,
+        }
+    }
+}
+
+// Iterators print the values they have left rather than the nodes they hold
+impl<'a, T: Debug> Debug for AvlTreeSetIter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, T: 'a> AvlTreeSetIter<'a, T> {
+    fn new(root: &'a AvlTree<T>, front: usize, back: usize) -> Self {
+        let mut iter = Self {
+            root,
+            front_nodes: Vec::new(),
+            back_nodes: Vec::new(),
+            front,
+            back: max(front, back),
+        };
+
+        iter.seek_front();
+        iter.seek_back();
+
+        iter
+    }
+
+    // Rebuilds the front stack so that its top is the value at the front position
+    fn seek_front(&mut self) {
+        self.front_nodes.clear();
+
+        if self.front == self.back {
+            return;
+        }
+
+        let mut index = self.front;
+        let mut current_tree = self.root;
+
+        while let Some(current_node) = current_tree {
+            let left_size = current_node.left_size();
+
+            if index <= left_size {
+                self.front_nodes.push(current_node);
+
+                if index == left_size {
+                    return;
+                }
+
+                current_tree = &current_node.left;
+            } else {
+                index -= left_size + 1;
+                current_tree = &current_node.right;
+            }
+        }
+    }
+
+    // Rebuilds the back stack so that its top is the value before the back position
+    fn seek_back(&mut self) {
+        self.back_nodes.clear();
+
+        if self.front == self.back {
+            return;
+        }
+
+        let mut index = self.back - 1;
+        let mut current_tree = self.root;
+
+        while let Some(current_node) = current_tree {
+            let left_size = current_node.left_size();
+
+            if index >= left_size {
+                self.back_nodes.push(current_node);
+
+                if index == left_size {
+                    return;
+                }
+
+                index -= left_size + 1;
+                current_tree = &current_node.right;
+            } else {
+                current_tree = &current_node.left;
+            }
+        }
+    }
+
+    fn push_left(&mut self, mut current_tree: &'a AvlTree<T>) {
+        while let Some(current_node) = current_tree {
+            self.front_nodes.push(current_node);
+            current_tree = &current_node.left;
+        }
+    }
+
+    fn push_right(&mut self, mut current_tree: &'a AvlTree<T>) {
+        while let Some(current_node) = current_tree {
+            self.back_nodes.push(current_node);
+            current_tree = &current_node.right;
+        }
+    }
+}
+
+impl<'a, T: 'a> Iterator for AvlTreeSetIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        let current_node = self.front_nodes.pop()?;
+        self.push_left(&current_node.right);
+        self.front += 1;
+
+        Some(&current_node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+
+        (len, Some(len))
+    }
+
+    // Skipping seeks from the root in O(log n) instead of popping one by one
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if n > 0 {
+            self.front = if n < self.len() {
+                self.front + n
+            } else {
+                self.back
+            };
+            self.seek_front();
+        }
+
+        self.next()
+    }
+}
+
+impl<'a, T: 'a> DoubleEndedIterator for AvlTreeSetIter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        let current_node = self.back_nodes.pop()?;
+        self.push_right(&current_node.left);
+        self.back -= 1;
+
+        Some(&current_node.value)
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+        if n > 0 {
+            self.back = if n < self.len() {
+                self.back - n
+            } else {
+                self.front
+            };
+            self.seek_back();
+        }
+
+        self.next_back()
+    }
+}
+
+impl<'a, T: 'a> ExactSizeIterator for AvlTreeSetIter<'a, T> {}
+
+// Addition of lifetime parameter for the set
+impl<'a, T: 'a, C: Compare<T>> AvlTreeSet<T, C> {
+    pub fn iter(&'a self) -> AvlTreeSetIter<'a, T> {
+        AvlTreeSetIter::new(&self.root, 0, self.len())
+    }
+
+    pub fn range<Q, R>(&'a self, range: R) -> AvlTreeSetIter<'a, T>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+        R: RangeBounds<Q>,
+    {
+        let (front, back) = self.range_positions(&range);
+
+        AvlTreeSetIter::new(&self.root, front, back)
+    }
+}
+
+// Owns the nodes on the way down the left spine, handing out each value once
+// its left subtree is done and then continuing with its right subtree
+pub struct AvlTreeSetIntoIter<T> {
+    prev_nodes: Vec<Box<AvlNode<T>>>,
+    len: usize,
+}
+
+impl<T: Debug> Debug for AvlTreeSetIntoIter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values = self.prev_nodes.iter().rev().flat_map(|node| {
+            let right = AvlTreeSetIter::new(&node.right, 0, size(&node.right));
+
+            Some(&node.value).into_iter().chain(right)
+        });
+
+        f.debug_list().entries(values).finish()
+    }
+}
+
+impl<T> AvlTreeSetIntoIter<T> {
+    fn new(root: AvlTree<T>) -> Self {
+        let mut iter = Self {
+            prev_nodes: Vec::new(),
+            len: size(&root),
+        };
+        iter.push_left(root);
+
+        iter
+    }
+
+    fn push_left(&mut self, mut current_tree: AvlTree<T>) {
+        while let Some(mut current_node) = current_tree {
+            current_tree = current_node.left.take();
+            self.prev_nodes.push(current_node);
+        }
+    }
+}
+
+impl<T> Iterator for AvlTreeSetIntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut current_node = self.prev_nodes.pop()?;
+        self.push_left(current_node.right.take());
+        self.len -= 1;
+
+        Some(current_node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for AvlTreeSetIntoIter<T> {}
+
+impl<T, C: Compare<T>> IntoIterator for AvlTreeSet<T, C> {
+    type Item = T;
+    type IntoIter = AvlTreeSetIntoIter<T>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        AvlTreeSetIntoIter::new(self.root.take())
+    }
+}
+
+impl<'a, T: 'a, C: Compare<T>> IntoIterator for &'a AvlTreeSet<T, C> {
+    type Item = &'a T;
+    type IntoIter = AvlTreeSetIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, C: Compare<T> + Default> FromIterator<T> for AvlTreeSet<T, C> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::default();
+        set.extend(iter);
+
+        set
+    }
+}
+
+impl<T, C: Compare<T>> Extend<T> for AvlTreeSet<T, C> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<'a, T: 'a + Copy, C: Compare<T>> Extend<&'a T> for AvlTreeSet<T, C> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Rng;
+    use std::hash::{Hash, Hasher};
+    use std::ops::Bound;
+
+    #[test]
+    fn insert_sorted_stays_balanced() {
+        let mut set = AvlTreeSet::new();
+
+        for i in 0..1000 {
+            assert!(set.insert(i));
+            assert!(set.is_balanced());
+        }
+
+        // A perfectly balanced tree of 1000 nodes has height 10, AVL allows ~1.44 log2(n)
+        assert!(set.height() <= 14);
+        assert!(set.iter().copied().eq(0..1000));
+    }
+
+    #[test]
+    fn insert_reverse_sorted_stays_balanced() {
+        let mut set = AvlTreeSet::new();
+
+        for i in (0..1000).rev() {
+            assert!(set.insert(i));
+            assert!(set.is_balanced());
+        }
+
+        assert!(set.height() <= 14);
+        assert!(set.iter().copied().eq(0..1000));
+    }
+
+    #[test]
+    fn insert_double_rotations() {
+        // Left-right case
+        let mut set = AvlTreeSet::new();
+        for i in &[3, 1, 2] {
+            set.insert(*i);
+        }
+        assert_eq!(set.root.as_ref().map(|node| node.value), Some(2));
+        assert_eq!(set.height(), 2);
+
+        // Right-left case
+        let mut set = AvlTreeSet::new();
+        for i in &[1, 3, 2] {
+            set.insert(*i);
+        }
+        assert_eq!(set.root.as_ref().map(|node| node.value), Some(2));
+        assert_eq!(set.height(), 2);
+    }
+
+    #[test]
+    fn insert_shuffled_stays_balanced() {
+        let mut set = AvlTreeSet::new();
+        let mut rng = Rng(1);
+
+        for _ in 0..2000 {
+            set.insert(rng.below(10_000));
+            assert!(set.is_balanced());
+        }
+
+        let mut values: Vec<_> = set.iter().copied().collect();
+        let len = values.len();
+        values.dedup();
+        assert_eq!(values.len(), len);
+        assert!(values.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn insert_builds_expected_structure() {
+        let mut set = AvlTreeSet::new();
+
+        assert!(set.insert(1)); // Insert new value
+        assert!(!set.insert(1)); // Should not insert existing value
+
+        assert!(set.insert(2)); // Insert another new value
+        assert_eq!(
+            // Checking the tree structure
+            set.root,
+            Some(Box::new(AvlNode {
+                value: 1,
+                left: None,
+                right: Some(Box::new(AvlNode {
+                    value: 2,
+                    left: None,
+                    right: None,
+                    height: 1,
+                    size: 1,
+                    summary: (),
+                })),
+                height: 2,
+                size: 2,
+                summary: (),
+            }))
+        );
+    }
+
+    #[test]
+    fn remove_stays_balanced() {
+        let mut set = AvlTreeSet::new();
+
+        for i in 0..1000 {
+            set.insert(i);
+        }
+
+        // Remove every other value, hitting leaves as well as inner nodes
+        for i in (0..1000).step_by(2) {
+            assert!(set.remove(&i));
+            assert!(!set.remove(&i));
+            assert!(set.is_balanced());
+        }
+
+        assert!(set.iter().copied().eq((1..1000).step_by(2)));
+
+        for i in (1..1000).step_by(2) {
+            assert!(set.remove(&i));
+            assert!(set.is_balanced());
+        }
+
+        assert_eq!(set.root, None);
+    }
+
+    #[test]
+    fn remove_node_with_two_children() {
+        let mut set = AvlTreeSet::new();
+
+        for i in &[4, 2, 6, 1, 3, 5, 7] {
+            set.insert(*i);
+        }
+
+        // The in-order successor replaces the removed root
+        assert_eq!(set.take(&4), Some(4));
+        assert_eq!(set.root.as_ref().map(|node| node.value), Some(5));
+        assert!(set.is_balanced());
+        assert!(set.iter().copied().eq(vec![1, 2, 3, 5, 6, 7]));
+    }
+
+    #[test]
+    fn take_missing_value() {
+        let mut set = AvlTreeSet::new();
+        assert_eq!(set.take(&1), None);
+
+        set.insert(1);
+        assert_eq!(set.take(&2), None);
+        assert_eq!(set.take(&1), Some(1));
+        assert_eq!(set.take(&1), None);
+    }
+
+    #[test]
+    fn pop_first_and_last() {
+        let mut set = AvlTreeSet::new();
+
+        for i in 0..100 {
+            set.insert(i);
+        }
+
+        for i in 0..50 {
+            assert_eq!(set.pop_first(), Some(i));
+            assert!(set.is_balanced());
+            assert_eq!(set.pop_last(), Some(99 - i));
+            assert!(set.is_balanced());
+        }
+
+        assert_eq!(set.pop_first(), None);
+        assert_eq!(set.pop_last(), None);
+    }
+
+    #[test]
+    fn iter_both_ends() {
+        let mut set = AvlTreeSet::new();
+
+        for i in 0..100 {
+            set.insert(i);
+        }
+
+        assert!(set.iter().rev().copied().eq((0..100).rev()));
+
+        // Alternate between the ends until they meet in the middle
+        let mut iter = set.iter();
+        for i in 0..50 {
+            assert_eq!(iter.next(), Some(&i));
+            assert_eq!(iter.next_back(), Some(&(99 - i)));
+        }
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+
+        let empty = AvlTreeSet::<i32>::new();
+        assert_eq!(empty.iter().next(), None);
+        assert_eq!(empty.iter().next_back(), None);
+    }
+
+    #[test]
+    fn range_bounds() {
+        let mut set = AvlTreeSet::new();
+
+        for i in (0..100).step_by(2) {
+            set.insert(i);
+        }
+
+        assert!(set.range(10..20).copied().eq(vec![10, 12, 14, 16, 18]));
+        assert!(set.range(11..=20).copied().eq(vec![12, 14, 16, 18, 20]));
+        assert!(set.range(..5).copied().eq(vec![0, 2, 4]));
+        assert!(set.range(95..).copied().eq(vec![96, 98]));
+        assert!(set.range(..).copied().eq((0..100).step_by(2)));
+        assert!(set
+            .range((Bound::Excluded(10), Bound::Excluded(16)))
+            .copied()
+            .eq(vec![12, 14]));
+
+        assert!(set
+            .range(20..30)
+            .rev()
+            .copied()
+            .eq(vec![28, 26, 24, 22, 20]));
+
+        // Ranges falling between or outside the values
+        assert_eq!(set.range(11..12).next(), None);
+        assert_eq!(set.range(11..12).next_back(), None);
+        assert_eq!(set.range(200..).next(), None);
+        assert_eq!(set.range(..-1).next_back(), None);
+        assert_eq!(set.range(4..4).next(), None);
+    }
+
+    #[test]
+    fn range_matches_filter() {
+        let mut set = AvlTreeSet::new();
+
+        for i in 0..64 {
+            set.insert(i * 3 % 64);
+        }
+
+        for start in 0..64 {
+            for end in start..64 {
+                let expected: Vec<_> = (start..end).collect();
+
+                assert!(set.range(start..end).copied().eq(expected.iter().copied()));
+                assert!(set
+                    .range(start..end)
+                    .rev()
+                    .copied()
+                    .eq(expected.iter().rev().copied()));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "range start is greater than range end in AvlTreeSet")]
+    fn range_start_after_end() {
+        let mut set = AvlTreeSet::new();
+        set.insert(1);
+
+        set.range((Bound::Included(3), Bound::Excluded(2)));
+    }
+
+    #[test]
+    fn len_rank_and_select() {
+        let mut set = AvlTreeSet::new();
+        assert_eq!(set.len(), 0);
+        assert!(set.is_empty());
+        assert_eq!(set.select(0), None);
+
+        for i in (0..200).rev().step_by(2) {
+            set.insert(i);
+        }
+
+        assert_eq!(set.len(), 100);
+        assert!(!set.is_empty());
+
+        for i in 0..100 {
+            assert_eq!(set.select(i), Some(&(i * 2 + 1)));
+            assert_eq!(set.rank(&(i * 2 + 1)), i);
+            assert_eq!(set.rank(&(i * 2)), i);
+        }
+
+        assert_eq!(set.select(100), None);
+        assert_eq!(set.rank(&1000), 100);
+
+        for i in (1..200).step_by(4) {
+            set.remove(&i);
+            assert!(set.is_balanced());
+        }
+
+        assert_eq!(set.len(), 50);
+        assert_eq!(set.select(0), Some(&3));
+        assert_eq!(set.rank(&3), 0);
+    }
+
+    #[test]
+    fn iter_seeks_and_reports_length() {
+        let mut set = AvlTreeSet::new();
+
+        for i in 0..100 {
+            set.insert(i);
+        }
+
+        assert_eq!(set.iter().len(), 100);
+        assert_eq!(set.range(10..20).len(), 10);
+        assert_eq!(set.range(10..=10).len(), 1);
+        assert!(set.iter().skip(37).copied().eq(37..100));
+        assert!(set.iter().rev().skip(37).copied().eq((0..63).rev()));
+        assert_eq!(set.iter().nth(99), Some(&99));
+        assert_eq!(set.iter().nth(100), None);
+
+        let mut iter = set.range(20..80);
+        assert_eq!(iter.nth(10), Some(&30));
+        assert_eq!(iter.nth_back(10), Some(&69));
+        assert_eq!(iter.len(), 38);
+        assert_eq!(iter.next(), Some(&31));
+        assert_eq!(iter.next_back(), Some(&68));
+        assert_eq!(iter.nth(36), None);
+        assert_eq!(iter.next_back(), None);
+    }
+
+    #[test]
+    fn percentile_from_select() {
+        let mut set = AvlTreeSet::new();
+
+        for sample in &[120, 15, 33, 87, 42, 9, 61, 250, 71, 18] {
+            set.insert(*sample);
+        }
+
+        // 90th percentile over the sorted samples
+        let index = set.len() * 9 / 10;
+        assert_eq!(set.select(index), Some(&250));
+        assert_eq!(set.iter().nth(index), set.select(index));
+    }
+
+    #[test]
+    fn split_off_at_every_position() {
+        for at in 0..=64 {
+            let mut set = AvlTreeSet::new();
+
+            for i in 0..64 {
+                set.insert(i * 2);
+            }
+
+            let right = set.split_off(&at);
+
+            assert!(set.is_balanced());
+            assert!(right.is_balanced());
+            assert!(set.iter().all(|value| *value < at));
+            assert!(right.iter().all(|value| *value >= at));
+            assert_eq!(set.len() + right.len(), 64);
+            assert_eq!(set.len(), ((at + 1) / 2) as usize);
+        }
+    }
+
+    #[test]
+    fn append_disjoint_and_overlapping() {
+        let mut left = AvlTreeSet::new();
+        let mut right = AvlTreeSet::new();
+
+        for i in 0..100 {
+            left.insert(i);
+        }
+
+        for i in 100..110 {
+            right.insert(i);
+        }
+
+        left.append(&mut right);
+        assert!(left.is_balanced());
+        assert!(left.iter().copied().eq(0..110));
+        assert!(right.is_empty());
+
+        // The other set may also hold the smaller values
+        let mut lower = AvlTreeSet::new();
+        for i in -50..0 {
+            lower.insert(i);
+        }
+
+        left.append(&mut lower);
+        assert!(left.is_balanced());
+        assert!(left.iter().copied().eq(-50..110));
+
+        let mut overlapping = AvlTreeSet::new();
+        for i in (100..200).step_by(5) {
+            overlapping.insert(i);
+        }
+
+        left.append(&mut overlapping);
+        assert!(left.is_balanced());
+        assert!(left
+            .iter()
+            .copied()
+            .eq((-50..110).chain((110..200).step_by(5))));
+    }
+
+    #[test]
+    fn join_around_pivot() {
+        let mut left = AvlTreeSet::new();
+        let mut right = AvlTreeSet::new();
+
+        for i in 0..3 {
+            left.insert(i);
+        }
+
+        for i in 4..500 {
+            right.insert(i);
+        }
+
+        let set = AvlTreeSet::join(left, 3, right);
+        assert!(set.is_balanced());
+        assert!(set.iter().copied().eq(0..500));
+
+        let set = AvlTreeSet::join(AvlTreeSet::new(), 1, AvlTreeSet::new());
+        assert!(set.iter().copied().eq(1..2));
+    }
+
+    #[test]
+    #[should_panic]
+    fn join_unordered() {
+        let mut left = AvlTreeSet::new();
+        left.insert(5);
+
+        AvlTreeSet::join(left, 3, AvlTreeSet::new());
+    }
+
+    #[test]
+    fn collect_and_extend() {
+        let mut set: AvlTreeSet<_> = vec![5, 3, 8, 3, 1].into_iter().collect();
+        assert!(set.iter().copied().eq(vec![1, 3, 5, 8]));
+        assert!(set.is_balanced());
+
+        set.extend(vec![2, 5, 13]);
+        set.extend(&[21, 1]);
+        assert!(set.iter().copied().eq(vec![1, 2, 3, 5, 8, 13, 21]));
+
+        let default: AvlTreeSet<i32> = Default::default();
+        assert!(default.is_empty());
+    }
+
+    #[test]
+    fn into_iter_owned_and_borrowed() {
+        let set: AvlTreeSet<_> = (0..100).rev().map(|i| i.to_string()).collect();
+
+        let mut borrowed = Vec::new();
+        for value in &set {
+            borrowed.push(value.clone());
+        }
+
+        let mut iter = set.into_iter();
+        assert_eq!(iter.len(), 100);
+        assert_eq!(iter.next(), Some("0".to_string()));
+        assert_eq!(iter.len(), 99);
+
+        let mut expected: Vec<_> = (0..100).map(|i| i.to_string()).collect();
+        expected.sort();
+        assert_eq!(borrowed, expected);
+        assert!(iter.eq(expected.into_iter().skip(1)));
+    }
+
+    #[test]
+    fn into_iter_drops_unvisited_values() {
+        let set: AvlTreeSet<_> = (0..100).map(|i| vec![i]).collect();
+
+        let mut iter = set.into_iter();
+        assert_eq!(iter.nth(10), Some(vec![10]));
+        drop(iter);
+    }
+
+    #[test]
+    fn equality_ignores_insertion_order() {
+        let ascending: AvlTreeSet<_> = (1..5).collect();
+        let descending: AvlTreeSet<_> = (1..5).rev().collect();
+
+        assert_eq!(ascending, descending);
+        assert!(!ascending.structural_eq(&descending));
+        assert!(ascending.structural_eq(&ascending.clone()));
+
+        let shorter: AvlTreeSet<_> = (1..4).collect();
+        assert_ne!(ascending, shorter);
+    }
+
+    #[test]
+    fn ordering_is_lexicographic() {
+        let set = |values: &[i32]| values.iter().copied().collect::<AvlTreeSet<_>>();
+
+        assert!(set(&[1, 2, 3]) < set(&[1, 2, 4]));
+        assert!(set(&[1, 2]) < set(&[1, 2, 3]));
+        assert!(set(&[2]) > set(&[1, 2, 3]));
+        assert!(set(&[]) < set(&[0]));
+        assert_eq!(set(&[3, 1, 2]).cmp(&set(&[1, 2, 3])), Ordering::Equal);
+    }
+
+    #[test]
+    fn hash_matches_equality() {
+        use std::collections::hash_map::DefaultHasher;
+
+        fn hash_of(set: &AvlTreeSet<i32>) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            set.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let ascending: AvlTreeSet<_> = (0..50).collect();
+        let descending: AvlTreeSet<_> = (0..50).rev().collect();
+
+        assert_eq!(hash_of(&ascending), hash_of(&descending));
+        assert_ne!(hash_of(&ascending), hash_of(&(0..49).collect()));
+    }
+
+    // Sorted input used to produce trees like this one before inserts were balanced
+    fn degenerate_set(len: usize) -> AvlTreeSet<usize> {
+        let mut root = None;
+
+        for value in (0..len).rev() {
+            let mut node = AvlNode::new(value);
+            node.right = root;
+            node.update();
+            root = Some(Box::new(node));
+        }
+
+        AvlTreeSet { root, cmp: Natural }
+    }
+
+    #[test]
+    fn deep_tree_drop_clone_and_debug() {
+        let set = degenerate_set(1_000_000);
+        assert_eq!(set.height(), 1_000_000);
+
+        let cloned = set.clone();
+        assert!(set.structural_eq(&cloned));
+        assert_eq!(set, cloned);
+
+        let debug = format!("{:?}", cloned);
+        assert!(debug.starts_with("{0, 1, 2"));
+        assert!(debug.ends_with("999998, 999999}"));
+
+        let mut iter = cloned.iter();
+        iter.next();
+        let debug = format!("{:?}", iter);
+        assert!(debug.starts_with("[1, 2, 3"));
+        assert!(debug.ends_with("999999]"));
+
+        drop(set);
+        drop(cloned);
+    }
+
+    #[test]
+    fn clone_keeps_shape() {
+        let set: AvlTreeSet<_> = (0..100).map(|i| i * 7 % 100).collect();
+        let cloned = set.clone();
+
+        assert!(set.structural_eq(&cloned));
+        assert!(cloned.is_balanced());
+        assert_eq!(format!("{:?}", set), format!("{:?}", cloned));
+        assert_eq!(format!("{:?}", AvlTreeSet::<i32>::new()), "{}");
+    }
+
+    #[test]
+    fn iterators_debug_remaining_values() {
+        let set: AvlTreeSet<_> = (0..10).rev().collect();
+
+        let mut iter = set.iter();
+        iter.next();
+        iter.next_back();
+        assert_eq!(format!("{:?}", iter), "[1, 2, 3, 4, 5, 6, 7, 8]");
+
+        let mut into_iter = set.clone().into_iter();
+        into_iter.nth(3);
+        assert_eq!(format!("{:?}", into_iter), "[4, 5, 6, 7, 8, 9]");
+        assert_eq!(format!("{:?}", set.range(7..)), "[7, 8, 9]");
+    }
+
+    #[test]
+    fn nearest_values() {
+        let set: AvlTreeSet<_> = (0..50).map(|i| i * 10).collect();
+
+        assert_eq!(set.first(), Some(&0));
+        assert_eq!(set.last(), Some(&490));
+
+        assert_eq!(set.floor(&25), Some(&20));
+        assert_eq!(set.floor(&20), Some(&20));
+        assert_eq!(set.floor(&-1), None);
+        assert_eq!(set.floor(&1000), Some(&490));
+
+        assert_eq!(set.ceiling(&25), Some(&30));
+        assert_eq!(set.ceiling(&30), Some(&30));
+        assert_eq!(set.ceiling(&491), None);
+        assert_eq!(set.ceiling(&-1000), Some(&0));
+
+        assert_eq!(set.predecessor(&30), Some(&20));
+        assert_eq!(set.predecessor(&31), Some(&30));
+        assert_eq!(set.predecessor(&0), None);
+
+        assert_eq!(set.successor(&30), Some(&40));
+        assert_eq!(set.successor(&29), Some(&30));
+        assert_eq!(set.successor(&490), None);
+
+        let empty = AvlTreeSet::<i32>::new();
+        assert_eq!(empty.first(), None);
+        assert_eq!(empty.last(), None);
+        assert_eq!(empty.floor(&0), None);
+        assert_eq!(empty.successor(&0), None);
+    }
+
+    #[test]
+    fn nearest_values_match_scan() {
+        let set: AvlTreeSet<_> = (0..200).map(|i| i * 37 % 211).collect();
+        let values: Vec<_> = set.iter().copied().collect();
+
+        for probe in -5..220 {
+            assert_eq!(
+                set.floor(&probe),
+                values.iter().rev().find(|v| **v <= probe)
+            );
+            assert_eq!(set.ceiling(&probe), values.iter().find(|v| **v >= probe));
+            assert_eq!(
+                set.predecessor(&probe),
+                values.iter().rev().find(|v| **v < probe)
+            );
+            assert_eq!(set.successor(&probe), values.iter().find(|v| **v > probe));
+        }
+    }
+
+    #[test]
+    fn lookups_by_borrowed_value() {
+        let mut set: AvlTreeSet<String> = ["apple", "banana", "cherry", "date", "fig"]
+            .iter()
+            .map(|value| value.to_string())
+            .collect();
+
+        assert!(set.contains("banana"));
+        assert!(!set.contains("grape"));
+        assert_eq!(set.get("cherry").map(String::as_str), Some("cherry"));
+        assert_eq!(set.rank("cherry"), 2);
+        assert_eq!(set.floor("c").map(String::as_str), Some("banana"));
+        assert_eq!(set.ceiling("c").map(String::as_str), Some("cherry"));
+        assert_eq!(set.predecessor("banana").map(String::as_str), Some("apple"));
+        assert_eq!(set.successor("fig"), None);
+
+        assert!(set
+            .range::<str, _>((Bound::Included("b"), Bound::Excluded("e")))
+            .eq(&["banana", "cherry", "date"]));
+
+        assert!(set.remove("date"));
+        assert_eq!(set.take("apple"), Some("apple".to_string()));
+
+        let tail = set.split_off("c");
+        assert!(set.iter().eq(&["banana"]));
+        assert!(tail.iter().eq(&["cherry", "fig"]));
+    }
+
+    #[test]
+    fn reversed_comparator() {
+        let mut set = AvlTreeSet::with_comparator(|a: &i32, b: &i32| b.cmp(a));
+
+        for i in 0..100 {
+            assert!(set.insert(i));
+            assert!(set.is_balanced());
+        }
+
+        assert!(!set.insert(50));
+        assert!(set.iter().copied().eq((0..100).rev()));
+        assert_eq!(set.first(), Some(&99));
+        assert_eq!(set.rank(&90), 9);
+        assert_eq!(set.floor(&-1), Some(&0));
+        assert_eq!(set.successor(&10), Some(&9));
+
+        // Ranges follow the comparator too, so they run from high to low
+        assert!(set
+            .range((Bound::Included(20), Bound::Included(15)))
+            .copied()
+            .eq((15..=20).rev()));
+
+        let tail = set.split_off(&49);
+        assert!(tail.iter().copied().eq((0..50).rev()));
+        assert!(tail.range(..3).copied().eq((4..50).rev()));
+    }
+
+    #[test]
+    fn case_insensitive_comparator() {
+        let mut set = AvlTreeSet::with_comparator(|a: &&str, b: &&str| {
+            a.to_lowercase().cmp(&b.to_lowercase())
+        });
+
+        assert!(set.insert("Banana"));
+        assert!(set.insert("apple"));
+        assert!(!set.insert("BANANA"));
+        assert!(set.insert("Cherry"));
+
+        assert!(set.contains(&"banana"));
+        assert_eq!(set.get(&"APPLE"), Some(&"apple"));
+        assert!(set.iter().eq(&["apple", "Banana", "Cherry"]));
+
+        let other = {
+            let mut other = AvlTreeSet::with_comparator(*set.comparator());
+            other.extend(vec!["CHERRY", "date"]);
+            other
+        };
+
+        assert!(set.union(&other).eq(&["apple", "Banana", "Cherry", "date"]));
+        assert!(set.intersection(&other).eq(&["Cherry"]));
+        assert!(set
+            .clone()
+            .into_difference(other)
+            .iter()
+            .eq(&["apple", "Banana"]));
+    }
+
+    #[test]
+    fn field_projected_comparator() {
+        #[derive(Debug, PartialEq)]
+        struct Task {
+            priority: u32,
+            name: &'static str,
+        }
+
+        let by_priority = |a: &Task, b: &Task| a.priority.cmp(&b.priority);
+        let mut set = AvlTreeSet::with_comparator(by_priority);
+
+        for (priority, name) in [(3, "write"), (1, "plan"), (2, "build"), (1, "again")].iter() {
+            set.insert(Task {
+                priority: *priority,
+                name,
+            });
+        }
+
+        assert!(set
+            .iter()
+            .map(|task| task.name)
+            .eq(vec!["plan", "build", "write"]));
+
+        // Any task with the same priority finds the stored one
+        let probe = Task {
+            priority: 1,
+            name: "",
+        };
+        assert_eq!(set.take(&probe).map(|task| task.name), Some("plan"));
+        assert!(set.iter().map(|task| task.name).eq(vec!["build", "write"]));
+    }
+
+    #[test]
+    #[should_panic(expected = "inserted value must keep the AvlTreeSet ordered")]
+    fn cursor_insert_checks_comparator() {
+        let mut set = AvlTreeSet::with_comparator(|a: &i32, b: &i32| b.cmp(a));
+        set.extend(vec![3, 2, 1]);
+
+        // 4 belongs before 3 in descending order
+        set.cursor_front_mut().insert_after(4);
+    }
+}
@@ -0,0 +1,173 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+
+use super::AvlTreeSet;
+use crate::compare::{Compare, Natural};
+use crate::node::build_sorted;
+
+// How the bulk constructors treat input that is not strictly increasing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortedInput {
+    // Every value must be greater than the one before it
+    Validate,
+    // Runs of equal values are reduced to their first value, values that are
+    // less than the one before them are still rejected
+    Dedup,
+}
+
+// Input of a bulk constructor was out of order at the given position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsortedError {
+    collection: &'static str,
+    index: usize,
+}
+
+impl UnsortedError {
+    // Position in the input of the first value that is out of order
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl fmt::Display for UnsortedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "value at index {} is out of order in sorted input for {}",
+            self.index, self.collection
+        )
+    }
+}
+
+impl Error for UnsortedError {}
+
+// Checks that the values are sorted as the input says and drops the repeated
+// ones if asked to, for every set that builds from sorted input
+pub(crate) fn check_sorted<T, C: Compare<T>>(
+    values: &mut Vec<T>,
+    cmp: &C,
+    input: SortedInput,
+    collection: &'static str,
+) -> Result<(), UnsortedError> {
+    for index in 1..values.len() {
+        match cmp.compare(&values[index - 1], &values[index]) {
+            Ordering::Less => {}
+            Ordering::Equal if input == SortedInput::Dedup => {}
+            _ => return Err(UnsortedError { collection, index }),
+        }
+    }
+
+    if input == SortedInput::Dedup {
+        values.dedup_by(|value, prev_value| cmp.compare(prev_value, value) == Ordering::Equal);
+    }
+
+    Ok(())
+}
+
+// Bulk construction checks the input in one pass and then builds the tree
+// bottom up in O(n), instead of n inserts with O(log n) searches and rotations
+
+impl<T: Ord> AvlTreeSet<T> {
+    pub fn from_sorted_iter<I>(iter: I, input: SortedInput) -> Result<Self, UnsortedError>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        Self::from_sorted_vec(iter.into_iter().collect(), input)
+    }
+
+    pub fn from_sorted_vec(values: Vec<T>, input: SortedInput) -> Result<Self, UnsortedError> {
+        Self::from_sorted_vec_with_comparator(values, Natural, input)
+    }
+}
+
+impl<T, C: Compare<T>> AvlTreeSet<T, C> {
+    // The values must be sorted by the given comparator
+    pub fn from_sorted_vec_with_comparator(
+        mut values: Vec<T>,
+        cmp: C,
+        input: SortedInput,
+    ) -> Result<Self, UnsortedError> {
+        check_sorted(&mut values, &cmp, input, "AvlTreeSet")?;
+
+        let len = values.len();
+
+        Ok(Self {
+            root: build_sorted(&mut values.into_iter(), len),
+            cmp,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_minimal_height_trees() {
+        for len in 0..300 {
+            let set = AvlTreeSet::from_sorted_iter(0..len, SortedInput::Validate).unwrap();
+
+            assert!(set.is_balanced());
+            assert_eq!(set.len(), len);
+            assert!(set.iter().copied().eq(0..len));
+
+            // A perfectly balanced tree of n values is ceil(log2(n + 1)) high
+            let min_height = (usize::BITS - len.leading_zeros()) as usize;
+            assert_eq!(set.height(), min_height);
+        }
+    }
+
+    #[test]
+    fn built_set_matches_inserted_set() {
+        let values: Vec<u64> = (0..100_000).map(|i| i * 3).collect();
+        let mut set = AvlTreeSet::from_sorted_vec(values.clone(), SortedInput::Validate).unwrap();
+        let inserted: AvlTreeSet<u64> = values.into_iter().collect();
+
+        assert_eq!(set, inserted);
+
+        // The built tree keeps working like any other
+        assert!(set.insert(1));
+        assert!(set.remove(&3));
+        assert_eq!(set.rank(&300), 100);
+        assert!(set.is_balanced());
+    }
+
+    #[test]
+    fn validate_rejects_duplicates_and_unsorted_values() {
+        let error = AvlTreeSet::from_sorted_vec(vec![1, 2, 2, 3], SortedInput::Validate);
+        assert_eq!(
+            error.map(|_| ()),
+            Err(UnsortedError {
+                collection: "AvlTreeSet",
+                index: 2
+            })
+        );
+
+        let error = AvlTreeSet::from_sorted_iter(vec![1, 5, 4], SortedInput::Validate);
+        assert_eq!(error.unwrap_err().index(), 2);
+    }
+
+    #[test]
+    fn dedup_drops_repeated_values() {
+        let set =
+            AvlTreeSet::from_sorted_vec(vec![1, 1, 2, 3, 3, 3, 4], SortedInput::Dedup).unwrap();
+
+        assert!(set.iter().copied().eq(1..=4));
+        assert!(set.is_balanced());
+
+        // Dedup does not sort the input
+        let error = AvlTreeSet::from_sorted_vec(vec![1, 1, 3, 2], SortedInput::Dedup);
+        assert_eq!(error.unwrap_err().index(), 3);
+    }
+
+    #[test]
+    fn dedup_keeps_first_of_equal_values() {
+        let cmp = |a: &(u32, char), b: &(u32, char)| a.0.cmp(&b.0);
+        let values = vec![(1, 'a'), (1, 'b'), (2, 'c'), (2, 'd')];
+        let set =
+            AvlTreeSet::from_sorted_vec_with_comparator(values, cmp, SortedInput::Dedup).unwrap();
+
+        assert!(set.iter().eq(&[(1, 'a'), (2, 'c')]));
+    }
+}
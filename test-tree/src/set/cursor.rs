@@ -0,0 +1,709 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt::{self, Debug};
+use std::ptr;
+
+use super::AvlTreeSet;
+use crate::compare::{Compare, Natural};
+use crate::node::{first, last, select, size, take_at, AvlNode, AvlTree};
+
+// Cursors point either at a value or at the "ghost" position past the last
+// value, which wraps around to the first one like `LinkedList` cursors do.
+// They keep the path from the root down to the current value, so moving to a
+// neighbour only walks the few nodes in between and a whole walk costs O(n).
+// Jumping to a position, including the wrap around, seeks from the root in
+// O(log n).
+
+fn next_index(index: usize, len: usize) -> usize {
+    (index + 1) % (len + 1)
+}
+
+fn prev_index(index: usize, len: usize) -> usize {
+    (index + len) % (len + 1)
+}
+
+fn is_left_child<T>(parent: &AvlNode<T>, child: &AvlNode<T>) -> bool {
+    parent
+        .left
+        .as_deref()
+        .is_some_and(|left| ptr::eq(left, child))
+}
+
+pub struct Cursor<'a, T, C = Natural> {
+    set: &'a AvlTreeSet<T, C>,
+    // Every node from the root down to the current one, empty at the ghost
+    path: Vec<&'a AvlNode<T>>,
+    index: usize,
+}
+
+impl<'a, T, C> Clone for Cursor<'a, T, C> {
+    fn clone(&self) -> Self {
+        Self {
+            set: self.set,
+            path: self.path.clone(),
+            index: self.index,
+        }
+    }
+}
+
+impl<'a, T: Debug, C> Debug for Cursor<'a, T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cursor")
+            .field("index", &self.index)
+            .field("current", &self.path.last().map(|node| &node.value))
+            .finish()
+    }
+}
+
+impl<'a, T, C: Compare<T>> Cursor<'a, T, C> {
+    fn new(set: &'a AvlTreeSet<T, C>, index: usize) -> Self {
+        let mut cursor = Self {
+            set,
+            path: Vec::new(),
+            index,
+        };
+        cursor.seek();
+
+        cursor
+    }
+
+    fn seek(&mut self) {
+        self.path.clear();
+
+        let mut index = self.index;
+        let mut tree = &self.set.root;
+
+        while let Some(current_node) = tree {
+            let left_size = current_node.left_size();
+            self.path.push(current_node);
+
+            match index.cmp(&left_size) {
+                Ordering::Less => tree = &current_node.left,
+                Ordering::Equal => return,
+                Ordering::Greater => {
+                    index -= left_size + 1;
+                    tree = &current_node.right;
+                }
+            }
+        }
+
+        // Past the last value
+        self.path.clear();
+    }
+
+    fn push_left(&mut self, mut tree: &'a AvlTree<T>) {
+        while let Some(current_node) = tree {
+            self.path.push(current_node);
+            tree = &current_node.left;
+        }
+    }
+
+    fn push_right(&mut self, mut tree: &'a AvlTree<T>) {
+        while let Some(current_node) = tree {
+            self.path.push(current_node);
+            tree = &current_node.right;
+        }
+    }
+
+    // Position of the current value, None at the ghost position
+    pub fn index(&self) -> Option<usize> {
+        Some(self.index).filter(|index| *index < self.set.len())
+    }
+
+    pub fn current(&self) -> Option<&'a T> {
+        self.path.last().map(|node| &node.value)
+    }
+
+    pub fn move_next(&mut self) {
+        let current_node = match self.path.last() {
+            Some(current_node) => *current_node,
+            None => {
+                self.index = 0;
+                self.push_left(&self.set.root);
+
+                return;
+            }
+        };
+
+        self.index += 1;
+
+        if current_node.right.is_some() {
+            self.push_left(&current_node.right);
+            return;
+        }
+
+        // Climb until coming up from a left subtree, whose parent is next
+        while let Some(child) = self.path.pop() {
+            if self
+                .path
+                .last()
+                .is_some_and(|parent| is_left_child(parent, child))
+            {
+                return;
+            }
+        }
+    }
+
+    pub fn move_prev(&mut self) {
+        let current_node = match self.path.last() {
+            Some(current_node) => *current_node,
+            None => {
+                self.index = self.set.len().saturating_sub(1);
+                self.push_right(&self.set.root);
+
+                return;
+            }
+        };
+
+        if current_node.left.is_some() {
+            self.index -= 1;
+            self.push_right(&current_node.left);
+            return;
+        }
+
+        while let Some(child) = self.path.pop() {
+            if self
+                .path
+                .last()
+                .is_some_and(|parent| !is_left_child(parent, child))
+            {
+                self.index -= 1;
+                return;
+            }
+        }
+
+        // Before the first value comes the ghost
+        self.index = self.set.len();
+    }
+
+    pub fn peek_next(&self) -> Option<&'a T> {
+        let current_node = match self.path.last() {
+            Some(current_node) => current_node,
+            None => return first(&self.set.root),
+        };
+
+        if current_node.right.is_some() {
+            return first(&current_node.right);
+        }
+
+        self.path
+            .windows(2)
+            .rev()
+            .find(|pair| is_left_child(pair[0], pair[1]))
+            .map(|pair| &pair[0].value)
+    }
+
+    pub fn peek_prev(&self) -> Option<&'a T> {
+        let current_node = match self.path.last() {
+            Some(current_node) => current_node,
+            None => return last(&self.set.root),
+        };
+
+        if current_node.left.is_some() {
+            return last(&current_node.left);
+        }
+
+        self.path
+            .windows(2)
+            .rev()
+            .find(|pair| !is_left_child(pair[0], pair[1]))
+            .map(|pair| &pair[0].value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Left,
+    Right,
+}
+
+fn attach<T>(mut parent: Box<AvlNode<T>>, side: Side, child: AvlTree<T>) -> Box<AvlNode<T>> {
+    match side {
+        Side::Left => parent.left = child,
+        Side::Right => parent.right = child,
+    }
+
+    parent
+}
+
+// A mutable cursor cannot keep references into a tree it changes, so while it
+// moves it takes the nodes on its path out of the tree instead: every
+// ancestor is detached from the child the path goes on through, and the
+// current subtree is held on its own. Before an edit, and when the cursor is
+// dropped, the nodes are put back together and the set is whole again, which
+// is why the set stays borrowed until the cursor is dropped. After an edit the
+// cursor seeks from the root on its next move.
+pub struct CursorMut<'a, T, C = Natural> {
+    set: &'a mut AvlTreeSet<T, C>,
+    // Ancestors of the current node, each missing the child on the path
+    path: Vec<(Box<AvlNode<T>>, Side)>,
+    // Subtree of the current node while the path is taken out, otherwise None
+    // and the whole tree is in the set
+    focus: AvlTree<T>,
+    index: usize,
+    len: usize,
+}
+
+impl<'a, T, C> Drop for CursorMut<'a, T, C> {
+    fn drop(&mut self) {
+        self.unzip();
+    }
+}
+
+impl<'a, T: Debug, C> Debug for CursorMut<'a, T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CursorMut")
+            .field("index", &self.index)
+            .field("current", &self.current())
+            .finish()
+    }
+}
+
+impl<'a, T, C> CursorMut<'a, T, C> {
+    fn new(set: &'a mut AvlTreeSet<T, C>, index: usize) -> Self {
+        let len = size(&set.root);
+
+        Self {
+            set,
+            path: Vec::new(),
+            focus: None,
+            index,
+            len,
+        }
+    }
+
+    // Takes the path down to the current value out of the tree
+    fn zip(&mut self) {
+        if self.focus.is_some() || self.index >= self.len {
+            return;
+        }
+
+        let mut index = self.index;
+        let mut tree = self.set.root.take();
+
+        while let Some(mut current_node) = tree {
+            let left_size = current_node.left_size();
+
+            match index.cmp(&left_size) {
+                Ordering::Less => {
+                    tree = current_node.left.take();
+                    self.path.push((current_node, Side::Left));
+                }
+                Ordering::Equal => {
+                    self.focus = Some(current_node);
+                    return;
+                }
+                Ordering::Greater => {
+                    index -= left_size + 1;
+                    tree = current_node.right.take();
+                    self.path.push((current_node, Side::Right));
+                }
+            }
+        }
+    }
+
+    // Puts the path back into the tree and the tree back into the set
+    fn unzip(&mut self) {
+        let mut tree = match self.focus.take() {
+            Some(current_node) => Some(current_node),
+            None => return,
+        };
+
+        while let Some((parent, side)) = self.path.pop() {
+            tree = Some(attach(parent, side, tree));
+        }
+
+        self.set.root = tree;
+    }
+
+    // Moves one level up, returning from which side the cursor came
+    fn ascend(&mut self) -> Option<Side> {
+        let (parent, side) = self.path.pop()?;
+        let child = self.focus.take();
+        self.focus = Some(attach(parent, side, child));
+
+        Some(side)
+    }
+
+    fn descend(&mut self, side: Side) {
+        let mut current_node = self.focus.take().unwrap();
+        self.focus = match side {
+            Side::Left => current_node.left.take(),
+            Side::Right => current_node.right.take(),
+        };
+        self.path.push((current_node, side));
+    }
+
+    fn focus_node(&self) -> Option<&AvlNode<T>> {
+        self.focus.as_deref()
+    }
+
+    pub fn index(&self) -> Option<usize> {
+        Some(self.index).filter(|index| *index < self.len)
+    }
+
+    pub fn current(&self) -> Option<&T> {
+        match self.focus_node() {
+            Some(current_node) => Some(&current_node.value),
+            None => select(&self.set.root, self.index),
+        }
+    }
+
+    pub fn move_next(&mut self) {
+        self.zip();
+
+        let has_right = match self.focus_node() {
+            Some(current_node) => current_node.right.is_some(),
+            None => {
+                self.index = next_index(self.index, self.len);
+                return;
+            }
+        };
+
+        self.index += 1;
+
+        if has_right {
+            self.descend(Side::Right);
+
+            while self.focus_node().is_some_and(|node| node.left.is_some()) {
+                self.descend(Side::Left);
+            }
+
+            return;
+        }
+
+        while let Some(side) = self.ascend() {
+            if side == Side::Left {
+                return;
+            }
+        }
+
+        // Came up to the root from the last value, the ghost is next
+        self.unzip();
+    }
+
+    pub fn move_prev(&mut self) {
+        self.zip();
+
+        let has_left = match self.focus_node() {
+            Some(current_node) => current_node.left.is_some(),
+            None => {
+                self.index = prev_index(self.index, self.len);
+                return;
+            }
+        };
+
+        if has_left {
+            self.index -= 1;
+            self.descend(Side::Left);
+
+            while self.focus_node().is_some_and(|node| node.right.is_some()) {
+                self.descend(Side::Right);
+            }
+
+            return;
+        }
+
+        while let Some(side) = self.ascend() {
+            if side == Side::Right {
+                self.index -= 1;
+                return;
+            }
+        }
+
+        self.index = self.len;
+        self.unzip();
+    }
+
+    pub fn peek_next(&self) -> Option<&T> {
+        let current_node = match self.focus_node() {
+            Some(current_node) => current_node,
+            None => return select(&self.set.root, next_index(self.index, self.len)),
+        };
+
+        if current_node.right.is_some() {
+            return first(&current_node.right);
+        }
+
+        self.path
+            .iter()
+            .rev()
+            .find(|(_, side)| *side == Side::Left)
+            .map(|(parent, _)| &parent.value)
+    }
+
+    pub fn peek_prev(&self) -> Option<&T> {
+        let current_node = match self.focus_node() {
+            Some(current_node) => current_node,
+            None => return select(&self.set.root, prev_index(self.index, self.len)),
+        };
+
+        if current_node.left.is_some() {
+            return last(&current_node.left);
+        }
+
+        self.path
+            .iter()
+            .rev()
+            .find(|(_, side)| *side == Side::Right)
+            .map(|(parent, _)| &parent.value)
+    }
+}
+
+impl<'a, T, C: Compare<T>> CursorMut<'a, T, C> {
+    pub fn as_cursor(&mut self) -> Cursor<'_, T, C> {
+        self.unzip();
+
+        Cursor::new(self.set, self.index)
+    }
+
+    // Inserts the value right before the current one, or as the last value at
+    // the ghost position. The cursor keeps pointing at the same value.
+    pub fn insert_before(&mut self, value: T) {
+        assert_ordered(&self.set.cmp, self.peek_prev(), &value, self.current());
+
+        self.unzip();
+        self.set.insert(value);
+        self.index += 1;
+        self.len += 1;
+    }
+
+    // Inserts the value right after the current one, or as the first value at
+    // the ghost position. The cursor keeps pointing at the same value.
+    pub fn insert_after(&mut self, value: T) {
+        assert_ordered(&self.set.cmp, self.current(), &value, self.peek_next());
+
+        self.unzip();
+        self.set.insert(value);
+
+        if self.index == self.len {
+            self.index += 1;
+        }
+
+        self.len += 1;
+    }
+
+    // Removes the current value and moves on to the next one
+    pub fn remove_current(&mut self) -> Option<T> {
+        self.unzip();
+
+        let value = take_at(&mut self.set.root, self.index);
+
+        if value.is_some() {
+            self.len -= 1;
+        }
+
+        value
+    }
+}
+
+fn assert_ordered<T, C: Compare<T>>(cmp: &C, lower: Option<&T>, value: &T, upper: Option<&T>) {
+    assert!(
+        lower.is_none_or(|lower| cmp.compare(lower, value) == Ordering::Less)
+            && upper.is_none_or(|upper| cmp.compare(value, upper) == Ordering::Less),
+        "inserted value must keep the AvlTreeSet ordered"
+    );
+}
+
+impl<T, C: Compare<T>> AvlTreeSet<T, C> {
+    pub fn cursor_front(&self) -> Cursor<'_, T, C> {
+        Cursor::new(self, 0)
+    }
+
+    // Cursor at the first value greater than or equal to the given one
+    pub fn lower_bound_cursor<Q>(&self, value: &Q) -> Cursor<'_, T, C>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        Cursor::new(self, self.rank(value))
+    }
+
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T, C> {
+        CursorMut::new(self, 0)
+    }
+
+    pub fn lower_bound_cursor_mut<Q>(&mut self, value: &Q) -> CursorMut<'_, T, C>
+    where
+        T: Borrow<Q>,
+        Q: ?Sized,
+        C: Compare<Q>,
+    {
+        let index = self.rank(value);
+
+        CursorMut::new(self, index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walk_both_directions() {
+        let set: AvlTreeSet<_> = (0..10).map(|i| i * 10).collect();
+
+        let mut cursor = set.lower_bound_cursor(&35);
+        assert_eq!(cursor.current(), Some(&40));
+        assert_eq!(cursor.index(), Some(4));
+        assert_eq!(cursor.peek_prev(), Some(&30));
+        assert_eq!(cursor.peek_next(), Some(&50));
+
+        cursor.move_prev();
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&20));
+
+        // Past the last value there is the ghost position, then it wraps around
+        let mut cursor = set.lower_bound_cursor(&90);
+        cursor.move_next();
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.index(), None);
+        assert_eq!(cursor.peek_prev(), Some(&90));
+        assert_eq!(cursor.peek_next(), Some(&0));
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&0));
+        assert_eq!(cursor.peek_prev(), None);
+
+        assert_eq!(set.lower_bound_cursor(&1000).current(), None);
+        assert_eq!(AvlTreeSet::<i32>::new().cursor_front().current(), None);
+    }
+
+    #[test]
+    fn lockstep_merge() {
+        let left: AvlTreeSet<_> = (0..20).step_by(3).collect();
+        let right: AvlTreeSet<_> = (0..20).step_by(4).collect();
+
+        // Walk two sets side by side, collecting the common values
+        let (mut left_cursor, mut right_cursor) = (left.cursor_front(), right.cursor_front());
+        let mut common = Vec::new();
+
+        while let (Some(left_value), Some(right_value)) =
+            (left_cursor.current(), right_cursor.current())
+        {
+            if left_value <= right_value {
+                left_cursor.move_next();
+            }
+
+            if right_value <= left_value {
+                right_cursor.move_next();
+            }
+
+            if left_value == right_value {
+                common.push(*left_value);
+            }
+        }
+
+        assert_eq!(common, vec![0, 12]);
+    }
+
+    #[test]
+    fn edit_in_place() {
+        let mut set: AvlTreeSet<_> = (0..10).map(|i| i * 10).collect();
+
+        let mut cursor = set.lower_bound_cursor_mut(&40);
+        cursor.insert_before(35);
+        cursor.insert_after(45);
+        assert_eq!(cursor.current(), Some(&40));
+        assert_eq!(cursor.peek_prev(), Some(&35));
+        assert_eq!(cursor.peek_next(), Some(&45));
+
+        assert_eq!(cursor.remove_current(), Some(40));
+        assert_eq!(cursor.current(), Some(&45));
+        assert_eq!(cursor.as_cursor().peek_prev(), Some(&35));
+        drop(cursor);
+
+        // Edits at the ghost position append at either end
+        let mut cursor = set.lower_bound_cursor_mut(&1000);
+        cursor.insert_before(100);
+        cursor.insert_after(-10);
+        assert_eq!(cursor.current(), None);
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&-10));
+        drop(cursor);
+
+        assert!(set.is_balanced());
+        assert!(set
+            .iter()
+            .copied()
+            .eq(vec![-10, 0, 10, 20, 30, 35, 45, 50, 60, 70, 80, 90, 100]));
+    }
+
+    #[test]
+    fn remove_everything_through_cursor() {
+        let mut set: AvlTreeSet<_> = (0..500).collect();
+
+        // Drop every value divisible by three while walking the set once
+        let mut cursor = set.cursor_front_mut();
+        while let Some(value) = cursor.current().copied() {
+            if value % 3 == 0 {
+                assert_eq!(cursor.remove_current(), Some(value));
+            } else {
+                cursor.move_next();
+            }
+        }
+        assert_eq!(cursor.remove_current(), None);
+        drop(cursor);
+
+        assert!(set.is_balanced());
+        assert!(set
+            .iter()
+            .copied()
+            .eq((0..500).filter(|value| value % 3 != 0)));
+    }
+
+    #[test]
+    fn walk_matches_iter() {
+        let mut set: AvlTreeSet<_> = (0..300).map(|i| i * 7 % 300).collect();
+        let values: Vec<i32> = set.iter().copied().collect();
+
+        let mut cursor = set.cursor_front();
+        for (index, value) in values.iter().enumerate() {
+            assert_eq!(cursor.index(), Some(index));
+            assert_eq!(cursor.current(), Some(value));
+            assert_eq!(cursor.peek_next(), values.get(index + 1));
+            cursor.move_next();
+        }
+        assert_eq!(cursor.current(), None);
+
+        for (index, value) in values.iter().enumerate().rev() {
+            cursor.move_prev();
+            assert_eq!(cursor.index(), Some(index));
+            assert_eq!(cursor.current(), Some(value));
+            assert_eq!(cursor.peek_prev(), index.checked_sub(1).map(|i| &values[i]));
+        }
+
+        // The mutable cursor walks the same way, also on after an edit
+        let mut cursor = set.lower_bound_cursor_mut(&100);
+        for value in 100..150 {
+            assert_eq!(cursor.current(), Some(&value));
+            assert_eq!(cursor.peek_prev(), Some(&(value - 1)));
+            assert_eq!(cursor.peek_next(), Some(&(value + 1)));
+            cursor.move_next();
+        }
+        assert_eq!(cursor.remove_current(), Some(150));
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&149));
+        for value in (0..149).rev() {
+            cursor.move_prev();
+            assert_eq!(cursor.current(), Some(&value));
+        }
+        cursor.move_prev();
+        assert_eq!(cursor.index(), None);
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&299));
+        drop(cursor);
+
+        assert!(set.is_balanced());
+        assert_eq!(set.len(), 299);
+        assert!(!set.contains(&150));
+    }
+
+    #[test]
+    #[should_panic]
+    fn insert_out_of_order() {
+        let mut set: AvlTreeSet<_> = (0..10).collect();
+
+        set.lower_bound_cursor_mut(&5).insert_before(7);
+    }
+}
@@ -0,0 +1,214 @@
+use std::iter::FusedIterator;
+
+use super::{AvlTreeSet, AvlTreeSetIntoIter, AvlTreeSetIter};
+use crate::compare::Compare;
+use crate::node::{build_sorted, height, size, take_at};
+
+// Removes and yields the values the predicate holds for, in sorted order. The
+// values the iterator has not got to when it is dropped stay in the set.
+//
+// Matches are first taken out of the tree one at a time in O(log n) each. Once
+// that adds up to more than rebuilding would cost, the remaining values are
+// moved out of the tree instead, and the kept ones are built into a new tree
+// in O(n) when the iterator is dropped.
+pub struct ExtractIf<'a, T, C, F>
+where
+    F: FnMut(&T) -> bool,
+{
+    set: &'a mut AvlTreeSet<T, C>,
+    pred: F,
+    // Position of the next value to check while removing in place
+    index: usize,
+    removed: usize,
+    // Kept values so far and the values still to check, once rebuilding
+    rebuild: Option<(Vec<T>, AvlTreeSetIntoIter<T>)>,
+}
+
+impl<'a, T, C, F> ExtractIf<'a, T, C, F>
+where
+    F: FnMut(&T) -> bool,
+{
+    // Every value before the current position is kept
+    fn start_rebuild(&mut self) {
+        let len = size(&self.set.root);
+        let mut values = AvlTreeSetIntoIter::new(self.set.root.take());
+        let mut kept = Vec::with_capacity(len);
+        kept.extend(values.by_ref().take(self.index));
+
+        self.rebuild = Some((kept, values));
+    }
+}
+
+impl<'a, T, C, F> Iterator for ExtractIf<'a, T, C, F>
+where
+    F: FnMut(&T) -> bool,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((kept, values)) = &mut self.rebuild {
+            for value in values {
+                if (self.pred)(&value) {
+                    return Some(value);
+                }
+
+                kept.push(value);
+            }
+
+            return None;
+        }
+
+        // Values are only borrowed for the search, the match is taken out by
+        // its position afterwards
+        let root = &self.set.root;
+        let len = size(root);
+        let pred = &mut self.pred;
+        let offset = AvlTreeSetIter::new(root, self.index, len).position(pred);
+
+        let offset = match offset {
+            Some(offset) => offset,
+            None => {
+                self.index = len;
+
+                return None;
+            }
+        };
+
+        self.index += offset;
+        self.removed += 1;
+
+        let value = take_at(&mut self.set.root, self.index);
+
+        if self.removed * height(&self.set.root) > size(&self.set.root) {
+            self.start_rebuild();
+        }
+
+        value
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.rebuild {
+            Some((_, values)) => (0, Some(values.len())),
+            None => (0, Some(size(&self.set.root) - self.index)),
+        }
+    }
+}
+
+impl<'a, T, C, F> FusedIterator for ExtractIf<'a, T, C, F> where F: FnMut(&T) -> bool {}
+
+impl<'a, T, C, F> Drop for ExtractIf<'a, T, C, F>
+where
+    F: FnMut(&T) -> bool,
+{
+    fn drop(&mut self) {
+        if let Some((mut kept, values)) = self.rebuild.take() {
+            kept.extend(values);
+
+            let len = kept.len();
+            self.set.root = build_sorted(&mut kept.into_iter(), len);
+        }
+    }
+}
+
+impl<T, C: Compare<T>> AvlTreeSet<T, C> {
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, T, C, F>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        ExtractIf {
+            set: self,
+            pred,
+            index: 0,
+            removed: 0,
+            rebuild: None,
+        }
+    }
+
+    // Keeps the values the predicate holds for, checking each of them once in
+    // sorted order
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.extract_if(|value| !f(value)).for_each(drop);
+    }
+
+    // Moves all values out in sorted order and leaves the set empty, even when
+    // the iterator is dropped early
+    pub fn drain(&mut self) -> AvlTreeSetIntoIter<T> {
+        AvlTreeSetIntoIter::new(self.root.take())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retain_keeps_matching_values() {
+        // Few removals stay in place, many removals rebuild the tree
+        for &step in [1000, 100, 7, 2, 1].iter() {
+            let mut set: AvlTreeSet<u32> = (0..10_000).collect();
+            let mut checked = Vec::new();
+
+            set.retain(|&value| {
+                checked.push(value);
+                value % step != 0
+            });
+
+            assert!(checked.into_iter().eq(0..10_000));
+            assert!(set.is_balanced());
+            assert!(set
+                .iter()
+                .copied()
+                .eq((0..10_000).filter(|value| value % step != 0)));
+        }
+    }
+
+    #[test]
+    fn extract_if_yields_removed_values() {
+        let mut set: AvlTreeSet<u32> = (0..1000).collect();
+
+        let removed: Vec<_> = set.extract_if(|value| value % 10 == 3).collect();
+
+        assert!(removed
+            .into_iter()
+            .eq((0..1000).filter(|value| value % 10 == 3)));
+        assert!(set.is_balanced());
+        assert_eq!(set.len(), 900);
+        assert!(!set.contains(&993));
+    }
+
+    #[test]
+    fn dropped_extract_if_keeps_the_rest() {
+        // Stops before and after switching over to rebuilding
+        for &taken in [3, 400].iter() {
+            let mut set: AvlTreeSet<u32> = (0..1000).collect();
+
+            let removed: Vec<_> = set.extract_if(|value| value % 2 == 0).take(taken).collect();
+
+            assert!(removed.into_iter().eq((0..).step_by(2).take(taken)));
+            assert!(set.is_balanced());
+            assert!(set
+                .iter()
+                .copied()
+                .eq((0..1000).filter(|&value| value % 2 == 1 || value >= taken as u32 * 2)));
+        }
+    }
+
+    #[test]
+    fn drain_empties_the_set() {
+        let mut set: AvlTreeSet<u32> = (0..100).rev().collect();
+
+        let mut drained = set.drain();
+        assert_eq!(drained.len(), 100);
+        assert_eq!(drained.next(), Some(0));
+        drop(drained);
+
+        assert!(set.is_empty());
+        assert!(set.drain().next().is_none());
+
+        set.insert(1);
+        assert!(set.drain().eq(vec![1]));
+    }
+}
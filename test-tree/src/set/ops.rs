@@ -0,0 +1,395 @@
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::marker::PhantomData;
+use std::ops::{BitAnd, BitOr, BitXor, Sub};
+
+use super::{AvlTreeSet, AvlTreeSetIter};
+use crate::compare::{Compare, Natural};
+use crate::node::{difference, intersection, symmetric_difference, union};
+
+// Lazy forms walk both sets in order side by side. They take the iterators of
+// whichever set they walk, the arena set uses them as well.
+
+pub struct Union<'a, T, C = Natural, I = AvlTreeSetIter<'a, T>>
+where
+    I: Iterator<Item = &'a T>,
+{
+    left: Peekable<I>,
+    right: Peekable<I>,
+    cmp: &'a C,
+    values: PhantomData<&'a T>,
+}
+
+impl<'a, T, C, I: Iterator<Item = &'a T>> Union<'a, T, C, I> {
+    pub(crate) fn new(left: I, right: I, cmp: &'a C) -> Self {
+        Self {
+            left: left.peekable(),
+            right: right.peekable(),
+            cmp,
+            values: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: 'a, C: Compare<T>, I: Iterator<Item = &'a T>> Iterator for Union<'a, T, C, I> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ordering = match (self.left.peek(), self.right.peek()) {
+            (Some(left_value), Some(right_value)) => self.cmp.compare(left_value, right_value),
+            (Some(_), None) => Ordering::Less,
+            (None, _) => Ordering::Greater,
+        };
+
+        match ordering {
+            Ordering::Less => self.left.next(),
+            Ordering::Equal => {
+                self.right.next();
+                self.left.next()
+            }
+            Ordering::Greater => self.right.next(),
+        }
+    }
+}
+
+pub struct Intersection<'a, T, C = Natural, I = AvlTreeSetIter<'a, T>>
+where
+    I: Iterator<Item = &'a T>,
+{
+    left: Peekable<I>,
+    right: Peekable<I>,
+    cmp: &'a C,
+    values: PhantomData<&'a T>,
+}
+
+impl<'a, T, C, I: Iterator<Item = &'a T>> Intersection<'a, T, C, I> {
+    pub(crate) fn new(left: I, right: I, cmp: &'a C) -> Self {
+        Self {
+            left: left.peekable(),
+            right: right.peekable(),
+            cmp,
+            values: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: 'a, C: Compare<T>, I: Iterator<Item = &'a T>> Iterator for Intersection<'a, T, C, I> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.cmp.compare(self.left.peek()?, self.right.peek()?) {
+                Ordering::Less => {
+                    self.left.next();
+                }
+                Ordering::Equal => {
+                    self.right.next();
+                    return self.left.next();
+                }
+                Ordering::Greater => {
+                    self.right.next();
+                }
+            }
+        }
+    }
+}
+
+pub struct Difference<'a, T, C = Natural, I = AvlTreeSetIter<'a, T>>
+where
+    I: Iterator<Item = &'a T>,
+{
+    left: Peekable<I>,
+    right: Peekable<I>,
+    cmp: &'a C,
+    values: PhantomData<&'a T>,
+}
+
+impl<'a, T, C, I: Iterator<Item = &'a T>> Difference<'a, T, C, I> {
+    pub(crate) fn new(left: I, right: I, cmp: &'a C) -> Self {
+        Self {
+            left: left.peekable(),
+            right: right.peekable(),
+            cmp,
+            values: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: 'a, C: Compare<T>, I: Iterator<Item = &'a T>> Iterator for Difference<'a, T, C, I> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let cmp = self.cmp;
+            let left_value = self.left.peek()?;
+
+            match self
+                .right
+                .peek()
+                .map(|right_value| cmp.compare(left_value, right_value))
+            {
+                None | Some(Ordering::Less) => return self.left.next(),
+                Some(Ordering::Equal) => {
+                    self.left.next();
+                    self.right.next();
+                }
+                Some(Ordering::Greater) => {
+                    self.right.next();
+                }
+            }
+        }
+    }
+}
+
+pub struct SymmetricDifference<'a, T, C = Natural, I = AvlTreeSetIter<'a, T>>
+where
+    I: Iterator<Item = &'a T>,
+{
+    left: Peekable<I>,
+    right: Peekable<I>,
+    cmp: &'a C,
+    values: PhantomData<&'a T>,
+}
+
+impl<'a, T, C, I: Iterator<Item = &'a T>> SymmetricDifference<'a, T, C, I> {
+    pub(crate) fn new(left: I, right: I, cmp: &'a C) -> Self {
+        Self {
+            left: left.peekable(),
+            right: right.peekable(),
+            cmp,
+            values: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: 'a, C: Compare<T>, I: Iterator<Item = &'a T>> Iterator
+    for SymmetricDifference<'a, T, C, I>
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let ordering = match (self.left.peek(), self.right.peek()) {
+                (Some(left_value), Some(right_value)) => self.cmp.compare(left_value, right_value),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => return None,
+            };
+
+            match ordering {
+                Ordering::Less => return self.left.next(),
+                Ordering::Equal => {
+                    self.left.next();
+                    self.right.next();
+                }
+                Ordering::Greater => return self.right.next(),
+            }
+        }
+    }
+}
+
+impl<'a, T: 'a, C: Compare<T>> AvlTreeSet<T, C> {
+    pub fn union(&'a self, other: &'a Self) -> Union<'a, T, C> {
+        Union::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    pub fn intersection(&'a self, other: &'a Self) -> Intersection<'a, T, C> {
+        Intersection::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    pub fn difference(&'a self, other: &'a Self) -> Difference<'a, T, C> {
+        Difference::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    pub fn symmetric_difference(&'a self, other: &'a Self) -> SymmetricDifference<'a, T, C> {
+        SymmetricDifference::new(self.iter(), other.iter(), &self.cmp)
+    }
+}
+
+// Consuming forms rebuild the trees with split and join, which is much faster
+// than walking both sets when one of them is small
+impl<T, C: Compare<T>> AvlTreeSet<T, C> {
+    pub fn into_union(mut self, mut other: Self) -> Self {
+        let cmp = &self.cmp;
+        let root = union(self.root.take(), other.root.take(), &mut |a: &T, b: &T| {
+            cmp.compare(a, b)
+        });
+        self.root = root;
+
+        self
+    }
+
+    pub fn into_intersection(mut self, mut other: Self) -> Self {
+        let cmp = &self.cmp;
+        let root = intersection(self.root.take(), other.root.take(), &mut |a: &T, b: &T| {
+            cmp.compare(a, b)
+        });
+        self.root = root;
+
+        self
+    }
+
+    pub fn into_difference(mut self, mut other: Self) -> Self {
+        let cmp = &self.cmp;
+        let root = difference(self.root.take(), other.root.take(), &mut |a: &T, b: &T| {
+            cmp.compare(a, b)
+        });
+        self.root = root;
+
+        self
+    }
+
+    pub fn into_symmetric_difference(mut self, mut other: Self) -> Self {
+        let cmp = &self.cmp;
+        let root =
+            symmetric_difference(self.root.take(), other.root.take(), &mut |a: &T, b: &T| {
+                cmp.compare(a, b)
+            });
+        self.root = root;
+
+        self
+    }
+}
+
+impl<T: Clone, C: Compare<T> + Clone> BitOr<&AvlTreeSet<T, C>> for &AvlTreeSet<T, C> {
+    type Output = AvlTreeSet<T, C>;
+
+    fn bitor(self, rhs: &AvlTreeSet<T, C>) -> AvlTreeSet<T, C> {
+        self.clone().into_union(rhs.clone())
+    }
+}
+
+impl<T: Clone, C: Compare<T> + Clone> BitAnd<&AvlTreeSet<T, C>> for &AvlTreeSet<T, C> {
+    type Output = AvlTreeSet<T, C>;
+
+    fn bitand(self, rhs: &AvlTreeSet<T, C>) -> AvlTreeSet<T, C> {
+        self.clone().into_intersection(rhs.clone())
+    }
+}
+
+impl<T: Clone, C: Compare<T> + Clone> Sub<&AvlTreeSet<T, C>> for &AvlTreeSet<T, C> {
+    type Output = AvlTreeSet<T, C>;
+
+    fn sub(self, rhs: &AvlTreeSet<T, C>) -> AvlTreeSet<T, C> {
+        self.clone().into_difference(rhs.clone())
+    }
+}
+
+impl<T: Clone, C: Compare<T> + Clone> BitXor<&AvlTreeSet<T, C>> for &AvlTreeSet<T, C> {
+    type Output = AvlTreeSet<T, C>;
+
+    fn bitxor(self, rhs: &AvlTreeSet<T, C>) -> AvlTreeSet<T, C> {
+        self.clone().into_symmetric_difference(rhs.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::values;
+    use std::collections::BTreeSet;
+
+    fn sets(left: &[u32], right: &[u32]) -> (AvlTreeSet<u32>, AvlTreeSet<u32>) {
+        let mut left_set = AvlTreeSet::new();
+        let mut right_set = AvlTreeSet::new();
+
+        for value in left {
+            left_set.insert(*value);
+        }
+
+        for value in right {
+            right_set.insert(*value);
+        }
+
+        (left_set, right_set)
+    }
+
+    #[test]
+    fn lazy_forms() {
+        let (left, right) = sets(&[1, 3, 5, 7, 9], &[3, 4, 5, 6]);
+
+        assert!(left.union(&right).copied().eq(vec![1, 3, 4, 5, 6, 7, 9]));
+        assert!(left.intersection(&right).copied().eq(vec![3, 5]));
+        assert!(left.difference(&right).copied().eq(vec![1, 7, 9]));
+        assert!(right.difference(&left).copied().eq(vec![4, 6]));
+        assert!(left
+            .symmetric_difference(&right)
+            .copied()
+            .eq(vec![1, 4, 6, 7, 9]));
+
+        let empty = AvlTreeSet::new();
+        assert!(left.union(&empty).eq(left.iter()));
+        assert_eq!(left.intersection(&empty).next(), None);
+        assert!(left.difference(&empty).eq(left.iter()));
+        assert_eq!(empty.difference(&left).next(), None);
+    }
+
+    #[test]
+    fn consuming_forms_match_btree_set() {
+        let sizes = [
+            (0, 0),
+            (0, 50),
+            (50, 0),
+            (1, 300),
+            (300, 1),
+            (40, 400),
+            (500, 500),
+        ];
+
+        for (seed, (left_size, right_size)) in sizes.iter().enumerate() {
+            let left_values = values(seed as u64, *left_size, 1000);
+            let right_values = values(seed as u64 + 100, *right_size, 1000);
+
+            let left_expected: BTreeSet<_> = left_values.iter().copied().collect();
+            let right_expected: BTreeSet<_> = right_values.iter().copied().collect();
+
+            let checks: Vec<(AvlTreeSet<u32>, BTreeSet<u32>)> = vec![
+                {
+                    let (left, right) = sets(&left_values, &right_values);
+                    (left.into_union(right), &left_expected | &right_expected)
+                },
+                {
+                    let (left, right) = sets(&left_values, &right_values);
+                    (
+                        left.into_intersection(right),
+                        &left_expected & &right_expected,
+                    )
+                },
+                {
+                    let (left, right) = sets(&left_values, &right_values);
+                    (
+                        left.into_difference(right),
+                        &left_expected - &right_expected,
+                    )
+                },
+                {
+                    let (left, right) = sets(&left_values, &right_values);
+                    (
+                        left.into_symmetric_difference(right),
+                        &left_expected ^ &right_expected,
+                    )
+                },
+            ];
+
+            for (result, expected) in checks {
+                assert!(result.is_balanced());
+                assert_eq!(result.len(), expected.len());
+                assert!(result.iter().eq(expected.iter()));
+            }
+        }
+    }
+
+    #[test]
+    fn operators() {
+        let (left, right) = sets(&[1, 2, 3], &[2, 3, 4]);
+
+        assert!((&left | &right).iter().copied().eq(vec![1, 2, 3, 4]));
+        assert!((&left & &right).iter().copied().eq(vec![2, 3]));
+        assert!((&left - &right).iter().copied().eq(vec![1]));
+        assert!((&left ^ &right).iter().copied().eq(vec![1, 4]));
+
+        // The operands are left untouched
+        assert_eq!(left.len(), 3);
+        assert_eq!(right.len(), 3);
+    }
+}
@@ -0,0 +1,240 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Debug};
+
+use rayon::iter::plumbing::{bridge, Consumer, Producer, ProducerCallback, UnindexedConsumer};
+use rayon::iter::{
+    FromParallelIterator, IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator,
+    ParallelIterator,
+};
+use rayon::slice::ParallelSliceMut;
+
+use super::{AvlTreeSet, AvlTreeSetIntoIter, AvlTreeSetIter};
+use crate::compare::Compare;
+use crate::node::{build_sorted, AvlTree};
+
+// Parallel iterator over the values of a set in sorted order. Every part of it
+// covers a range of positions, so splitting a part divides the tree by the
+// subtree sizes and both halves seek their first value from the root in
+// O(log n). Being indexed, it keeps the order through `collect`, `zip` and
+// `enumerate`.
+pub struct AvlTreeSetParIter<'a, T> {
+    root: &'a AvlTree<T>,
+    len: usize,
+}
+
+impl<'a, T: Debug> Debug for AvlTreeSetParIter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        AvlTreeSetIter::new(self.root, 0, self.len).fmt(f)
+    }
+}
+
+impl<'a, T: Sync + 'a> ParallelIterator for AvlTreeSetParIter<'a, T> {
+    type Item = &'a T;
+
+    fn drive_unindexed<S>(self, consumer: S) -> S::Result
+    where
+        S: UnindexedConsumer<Self::Item>,
+    {
+        bridge(self, consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'a, T: Sync + 'a> IndexedParallelIterator for AvlTreeSetParIter<'a, T> {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn drive<S: Consumer<Self::Item>>(self, consumer: S) -> S::Result {
+        bridge(self, consumer)
+    }
+
+    fn with_producer<CB: ProducerCallback<Self::Item>>(self, callback: CB) -> CB::Output {
+        callback.callback(SetProducer {
+            root: self.root,
+            front: 0,
+            back: self.len,
+        })
+    }
+}
+
+// The values at the positions front..back
+#[derive(Clone, Copy)]
+struct SetProducer<'a, T> {
+    root: &'a AvlTree<T>,
+    front: usize,
+    back: usize,
+}
+
+impl<'a, T: Sync + 'a> Producer for SetProducer<'a, T> {
+    type Item = &'a T;
+    type IntoIter = AvlTreeSetIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        AvlTreeSetIter::new(self.root, self.front, self.back)
+    }
+
+    fn split_at(self, index: usize) -> (Self, Self) {
+        let middle = self.front + index;
+
+        (
+            SetProducer {
+                back: middle,
+                ..self
+            },
+            SetProducer {
+                front: middle,
+                ..self
+            },
+        )
+    }
+}
+
+// Gives `par_iter` through the blanket `IntoParallelRefIterator` of rayon
+impl<'a, T: Sync + 'a, C: Compare<T>> IntoParallelIterator for &'a AvlTreeSet<T, C> {
+    type Item = &'a T;
+    type Iter = AvlTreeSetParIter<'a, T>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        AvlTreeSetParIter {
+            root: &self.root,
+            len: self.len(),
+        }
+    }
+}
+
+// Sorts the values in parallel and builds the tree in one go. The sort is
+// stable, so out of equal values the first one is kept, like `FromIterator`
+// does. The sorted values are trusted as they are: a comparator that is not a
+// consistent total order gets a tree out of order, like it would from
+// inserting the values one by one, rather than a panic.
+impl<T: Send, C: Compare<T> + Default + Sync> FromParallelIterator<T> for AvlTreeSet<T, C> {
+    fn from_par_iter<I>(par_iter: I) -> Self
+    where
+        I: IntoParallelIterator<Item = T>,
+    {
+        let mut values: Vec<T> = par_iter.into_par_iter().collect();
+        let cmp = C::default();
+
+        values.par_sort_by(|a, b| cmp.compare(a, b));
+        values.dedup_by(|value, kept| cmp.compare(kept, value) == Ordering::Equal);
+
+        let len = values.len();
+
+        AvlTreeSet {
+            root: build_sorted(&mut values.into_iter(), len),
+            cmp,
+        }
+    }
+}
+
+impl<T: Send + Sync, C: Compare<T>> AvlTreeSet<T, C> {
+    // Keeps the values the predicate holds for. The predicate runs in parallel,
+    // then the tree gets rebuilt out of the kept values in O(n).
+    pub fn par_retain<F>(&mut self, f: F)
+    where
+        F: Fn(&T) -> bool + Sync,
+    {
+        let keep: Vec<bool> = self.par_iter().map(&f).collect();
+        let len = keep.iter().filter(|&&keep| keep).count();
+
+        if len == keep.len() {
+            return;
+        }
+
+        let mut kept = AvlTreeSetIntoIter::new(self.root.take())
+            .zip(keep)
+            .filter_map(|(value, keep)| if keep { Some(value) } else { None });
+        self.root = build_sorted(&mut kept, len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Orders pairs by their first field only
+    #[derive(Debug, Default)]
+    struct ByKey;
+
+    impl Compare<(u32, u32)> for ByKey {
+        fn compare(&self, left: &(u32, u32), right: &(u32, u32)) -> Ordering {
+            left.0.cmp(&right.0)
+        }
+    }
+
+    #[test]
+    fn par_iter_matches_iter() {
+        let set: AvlTreeSet<u64> = (0..100_000).map(|i| i * 3).collect();
+
+        assert_eq!(set.par_iter().len(), set.len());
+        assert_eq!(set.par_iter().sum::<u64>(), set.iter().sum());
+        assert_eq!(
+            set.par_iter().with_min_len(7).collect::<Vec<_>>(),
+            set.iter().collect::<Vec<_>>()
+        );
+        assert!(set
+            .par_iter()
+            .enumerate()
+            .all(|(index, &value)| value == index as u64 * 3));
+
+        let empty = AvlTreeSet::<u64>::new();
+        assert_eq!(empty.par_iter().count(), 0);
+    }
+
+    #[test]
+    fn from_par_iter_sorts_and_dedups() {
+        let set: AvlTreeSet<_> = (0..50_000u32)
+            .into_par_iter()
+            .map(|i| i * 7 % 10_000)
+            .collect();
+
+        assert!(set.is_balanced());
+        assert!(set.iter().copied().eq(0..10_000));
+
+        // Out of equal values the one first in the input wins
+        let set: AvlTreeSet<_, ByKey> = (0..1000).into_par_iter().map(|i| (i % 100, i)).collect();
+
+        assert!(set.iter().copied().eq((0..100).map(|i| (i, i))));
+    }
+
+    #[test]
+    fn from_par_iter_trusts_an_inconsistent_comparator() {
+        // Says every pair is out of order, which no sort can satisfy
+        #[derive(Default)]
+        struct Contrary;
+
+        impl Compare<u32> for Contrary {
+            fn compare(&self, _: &u32, _: &u32) -> Ordering {
+                Ordering::Greater
+            }
+        }
+
+        let set: AvlTreeSet<u32, Contrary> = (0..1000).into_par_iter().collect();
+
+        assert_eq!(set.len(), 1000);
+        assert!(set.is_balanced());
+    }
+
+    #[test]
+    fn par_retain_rebuilds_balanced() {
+        let mut set: AvlTreeSet<u32> = (0..100_000).collect();
+
+        set.par_retain(|value| value % 3 == 0);
+        assert!(set.is_balanced());
+        assert_eq!(set.len(), 33_334);
+        assert!(set
+            .iter()
+            .copied()
+            .eq((0..100_000).filter(|value| value % 3 == 0)));
+
+        set.par_retain(|_| true);
+        assert_eq!(set.len(), 33_334);
+
+        set.par_retain(|_| false);
+        assert!(set.is_empty());
+    }
+}
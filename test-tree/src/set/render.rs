@@ -0,0 +1,181 @@
+use std::fmt::{Debug, Write};
+
+use super::AvlTreeSet;
+use crate::node::NodeView;
+
+// Values are shown with their `Debug` format in both renderings
+
+// Escapes a label for a double quoted Graphviz string
+fn dot_label<T: Debug>(value: &T) -> String {
+    format!("{:?}", value)
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+}
+
+// Lines of the subtree with the right side on top and the left side below, so
+// that reading the values from the bottom up gives them in sorted order. The
+// prefixes continue the lines of the ancestors for the lines above the value,
+// the value itself and the lines below it.
+fn write_sideways<'a, N>(output: &mut String, node: N, above: &str, current: &str, below: &str)
+where
+    N: NodeView<'a>,
+    N::Value: Debug,
+{
+    let (left, right) = node.children();
+
+    if let Some(right) = right {
+        write_sideways(
+            output,
+            right,
+            &format!("{}    ", above),
+            &format!("{}┌── ", above),
+            &format!("{}│   ", above),
+        );
+    }
+
+    writeln!(output, "{}{:?}", current, node.value()).unwrap();
+
+    if let Some(left) = left {
+        write_sideways(
+            output,
+            left,
+            &format!("{}│   ", below),
+            &format!("{}└── ", below),
+            &format!("{}    ", below),
+        );
+    }
+}
+
+// Graphviz digraph of the tree with the height and balance factor of every
+// node. Missing children are drawn as points whenever the other child is
+// present, otherwise a lone child could not be told left from right.
+pub(crate) fn to_dot<'a, N>(root: Option<N>, name: &str) -> String
+where
+    N: NodeView<'a>,
+    N::Value: Debug,
+{
+    let mut output = format!("digraph {} {{\n", name);
+    let mut nodes: Vec<(N, usize)> = root.into_iter().map(|node| (node, 0)).collect();
+    let mut next_id = 1;
+
+    while let Some((node, id)) = nodes.pop() {
+        let (left, right) = node.children();
+        let balance_factor =
+            left.map_or(0, N::cached_height) as isize - right.map_or(0, N::cached_height) as isize;
+
+        writeln!(
+            output,
+            "    n{} [label=\"{}\\nh={} bf={}\"];",
+            id,
+            dot_label(node.value()),
+            node.cached_height(),
+            balance_factor
+        )
+        .unwrap();
+
+        if left.is_none() && right.is_none() {
+            continue;
+        }
+
+        for child in [left, right].iter() {
+            match child {
+                Some(child) => {
+                    writeln!(output, "    n{} -> n{};", id, next_id).unwrap();
+                    nodes.push((*child, next_id));
+                }
+                None => {
+                    writeln!(output, "    n{} [shape=point];", next_id).unwrap();
+                    writeln!(output, "    n{} -> n{};", id, next_id).unwrap();
+                }
+            }
+
+            next_id += 1;
+        }
+    }
+
+    output.push_str("}\n");
+
+    output
+}
+
+// Sideways drawing of the tree for terminals, with the root on the left and
+// the greater values on top
+pub(crate) fn to_pretty_string<'a, N>(root: Option<N>) -> String
+where
+    N: NodeView<'a>,
+    N::Value: Debug,
+{
+    let mut output = String::new();
+
+    if let Some(root) = root {
+        write_sideways(&mut output, root, "", "", "");
+    }
+
+    output
+}
+
+impl<T: Debug, C> AvlTreeSet<T, C> {
+    pub fn to_dot(&self) -> String {
+        to_dot(self.root.as_deref(), "AvlTreeSet")
+    }
+
+    pub fn to_pretty_string(&self) -> String {
+        to_pretty_string(self.root.as_deref())
+    }
+
+    pub fn pretty_print(&self) {
+        print!("{}", self.to_pretty_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pretty_string_draws_sideways() {
+        let set: AvlTreeSet<_> = (1..=7).collect();
+
+        assert_eq!(
+            set.to_pretty_string(),
+            "    ┌── 7\n\
+             ┌── 6\n\
+             │   └── 5\n\
+             4\n\
+             │   ┌── 3\n\
+             └── 2\n\
+             \x20   └── 1\n"
+        );
+
+        assert_eq!(AvlTreeSet::<u32>::new().to_pretty_string(), "");
+    }
+
+    #[test]
+    fn dot_shows_heights_and_balance_factors() {
+        let set: AvlTreeSet<_> = vec![2, 1, 3, 4].into_iter().collect();
+
+        assert_eq!(
+            set.to_dot(),
+            "digraph AvlTreeSet {\n\
+             \x20   n0 [label=\"2\\nh=3 bf=-1\"];\n\
+             \x20   n0 -> n1;\n\
+             \x20   n0 -> n2;\n\
+             \x20   n2 [label=\"3\\nh=2 bf=-1\"];\n\
+             \x20   n3 [shape=point];\n\
+             \x20   n2 -> n3;\n\
+             \x20   n2 -> n4;\n\
+             \x20   n4 [label=\"4\\nh=1 bf=0\"];\n\
+             \x20   n1 [label=\"1\\nh=1 bf=0\"];\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn dot_escapes_labels() {
+        let set: AvlTreeSet<_> = vec!["say \"hi\""].into_iter().collect();
+
+        assert!(set
+            .to_dot()
+            .contains(r#"n0 [label="\"say \\\"hi\\\"\"\nh=1 bf=0"];"#));
+    }
+}
@@ -0,0 +1,367 @@
+use std::cmp::{max, Ordering};
+use std::error::Error;
+use std::fmt;
+
+use super::AvlTreeSet;
+use crate::compare::Compare;
+use crate::node::NodeView;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    // The value is not between the values of its ancestors
+    Order,
+    Height { cached: usize, actual: usize },
+    Size { cached: usize, actual: usize },
+    BalanceFactor(isize),
+}
+
+// Broken invariant of the node at the given path, which starts at `root` and
+// follows `left` and `right` links, like `root.left.right`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvariantError {
+    collection: &'static str,
+    path: String,
+    violation: Violation,
+}
+
+impl InvariantError {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn violation(&self) -> &Violation {
+        &self.violation
+    }
+}
+
+impl fmt::Display for InvariantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} node at {} ", self.collection, self.path)?;
+
+        match &self.violation {
+            Violation::Order => write!(f, "is out of order with its ancestors"),
+            Violation::Height { cached, actual } => {
+                write!(f, "caches height {} but is {} high", cached, actual)
+            }
+            Violation::Size { cached, actual } => {
+                write!(f, "caches size {} but its subtree holds {}", cached, actual)
+            }
+            Violation::BalanceFactor(balance_factor) => {
+                write!(f, "has balance factor {}", balance_factor)
+            }
+        }
+    }
+}
+
+impl Error for InvariantError {}
+
+// A node to check, with the closest ancestors it must lie between
+struct Frame<'a, N: NodeView<'a>> {
+    node: N,
+    parent: Option<(usize, &'static str)>,
+    lower: Option<&'a N::Value>,
+    upper: Option<&'a N::Value>,
+}
+
+// A checked node with the position of its parent among the checked nodes and
+// the side of the parent it hangs on
+type Visited<N> = (N, Option<(usize, &'static str)>);
+
+// Builds the path of a visited node out of the links to the visited parents
+fn path<N>(visited: &[Visited<N>], mut index: usize) -> String {
+    let mut sides = Vec::new();
+
+    while let Some((parent, side)) = visited[index].1 {
+        sides.push(side);
+        index = parent;
+    }
+
+    sides.push("root");
+    sides.reverse();
+
+    sides.join(".")
+}
+
+// Checks the ordering and the cached heights and sizes of every node, and that
+// the tree is balanced. The first pass checks the ordering top down, the
+// second one goes through the nodes in reverse, so every node is checked
+// against the cached values of its children after the children themselves.
+// This reports the deepest broken node and needs no recursion.
+pub(crate) fn validate_tree<'a, N, C>(
+    root: Option<N>,
+    cmp: &C,
+    collection: &'static str,
+) -> Result<(), InvariantError>
+where
+    N: NodeView<'a>,
+    C: Compare<N::Value>,
+{
+    let mut visited: Vec<Visited<N>> = Vec::with_capacity(root.map_or(0, N::cached_size));
+    let mut frames: Vec<Frame<'a, N>> = root
+        .into_iter()
+        .map(|node| Frame {
+            node,
+            parent: None,
+            lower: None,
+            upper: None,
+        })
+        .collect();
+
+    while let Some(Frame {
+        node,
+        parent,
+        lower,
+        upper,
+    }) = frames.pop()
+    {
+        let index = visited.len();
+        visited.push((node, parent));
+
+        let value = node.value();
+
+        if lower.is_some_and(|lower| cmp.compare(lower, value) != Ordering::Less)
+            || upper.is_some_and(|upper| cmp.compare(value, upper) != Ordering::Less)
+        {
+            return Err(InvariantError {
+                collection,
+                path: path(&visited, index),
+                violation: Violation::Order,
+            });
+        }
+
+        let (left, right) = node.children();
+
+        if let Some(right) = right {
+            frames.push(Frame {
+                node: right,
+                parent: Some((index, "right")),
+                lower: Some(value),
+                upper,
+            });
+        }
+
+        if let Some(left) = left {
+            frames.push(Frame {
+                node: left,
+                parent: Some((index, "left")),
+                lower,
+                upper: Some(value),
+            });
+        }
+    }
+
+    for (index, (node, _)) in visited.iter().enumerate().rev() {
+        let (left, right) = node.children();
+        let (left_height, right_height) = (
+            left.map_or(0, N::cached_height),
+            right.map_or(0, N::cached_height),
+        );
+        let actual_height = 1 + max(left_height, right_height);
+        let actual_size = 1 + left.map_or(0, N::cached_size) + right.map_or(0, N::cached_size);
+        let balance_factor = left_height as isize - right_height as isize;
+
+        let violation = if node.cached_height() != actual_height {
+            Violation::Height {
+                cached: node.cached_height(),
+                actual: actual_height,
+            }
+        } else if node.cached_size() != actual_size {
+            Violation::Size {
+                cached: node.cached_size(),
+                actual: actual_size,
+            }
+        } else if balance_factor.abs() > 1 {
+            Violation::BalanceFactor(balance_factor)
+        } else {
+            continue;
+        };
+
+        return Err(InvariantError {
+            collection,
+            path: path(&visited, index),
+            violation,
+        });
+    }
+
+    Ok(())
+}
+
+// Only compiled into debug builds, release builds are expected to rely on the
+// invariants without paying for the checks
+impl<T, C: Compare<T>> AvlTreeSet<T, C> {
+    pub fn validate(&self) -> Result<(), InvariantError> {
+        validate_tree(self.root.as_deref(), &self.cmp, "AvlTreeSet")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::AvlNode;
+    use crate::testing::Rng;
+    use std::collections::BTreeSet;
+
+    fn node(value: u32, left: Option<AvlNode<u32>>, right: Option<AvlNode<u32>>) -> AvlNode<u32> {
+        let mut node = AvlNode::new(value);
+        node.left = left.map(Box::new);
+        node.right = right.map(Box::new);
+        node.update();
+
+        node
+    }
+
+    fn set(root: AvlNode<u32>) -> AvlTreeSet<u32> {
+        let mut set = AvlTreeSet::new();
+        set.root = Some(Box::new(root));
+
+        set
+    }
+
+    #[test]
+    fn valid_sets_pass() {
+        let mut set = AvlTreeSet::new();
+        assert_eq!(set.validate(), Ok(()));
+
+        for i in 0..100 {
+            set.insert(i);
+            assert_eq!(set.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn reports_values_out_of_order() {
+        // 5 hangs under the left link of 4, so it must be less than 4
+        let root = node(4, Some(node(2, None, Some(node(5, None, None)))), None);
+        let error = set(node(6, Some(root), Some(node(7, None, None))))
+            .validate()
+            .unwrap_err();
+
+        assert_eq!(error.path(), "root.left.left.right");
+        assert_eq!(error.violation(), &Violation::Order);
+    }
+
+    #[test]
+    fn reports_wrong_cached_values() {
+        let mut root = node(2, Some(node(1, None, None)), Some(node(3, None, None)));
+        root.left.as_mut().unwrap().height = 3;
+        let error = set(root).validate().unwrap_err();
+
+        assert_eq!(error.path(), "root.left");
+        assert_eq!(
+            error.violation(),
+            &Violation::Height {
+                cached: 3,
+                actual: 1
+            }
+        );
+
+        let mut root = node(2, Some(node(1, None, None)), Some(node(3, None, None)));
+        root.right.as_mut().unwrap().size = 5;
+        let error = set(root).validate().unwrap_err();
+
+        assert_eq!(error.path(), "root.right");
+        assert_eq!(
+            error.to_string(),
+            "AvlTreeSet node at root.right caches size 5 but its subtree holds 1"
+        );
+    }
+
+    #[test]
+    fn reports_unbalanced_nodes() {
+        let error = set(node(
+            1,
+            None,
+            Some(node(2, None, Some(node(3, None, None)))),
+        ))
+        .validate()
+        .unwrap_err();
+
+        assert_eq!(error.path(), "root");
+        assert_eq!(error.violation(), &Violation::BalanceFactor(-2));
+    }
+
+    // Runs the same random operations on both sets, validating the tree and
+    // comparing it with the model after every step
+    fn run_model(seed: u64, steps: usize, modulo: u64) {
+        let mut rng = Rng(seed);
+        let mut set = AvlTreeSet::new();
+        let mut model = BTreeSet::new();
+
+        for step in 0..steps {
+            let value = rng.below(modulo);
+
+            let operation = match rng.below(20) {
+                0..=7 => {
+                    assert_eq!(set.insert(value), model.insert(value));
+                    "insert"
+                }
+                8..=11 => {
+                    assert_eq!(set.remove(&value), model.remove(&value));
+                    "remove"
+                }
+                12 | 13 => {
+                    assert_eq!(set.take(&value), model.take(&value));
+                    "take"
+                }
+                14 | 15 => {
+                    assert_eq!(set.pop_first(), model.pop_first());
+                    assert_eq!(set.pop_last(), model.pop_last());
+                    "pop"
+                }
+                16 | 17 => {
+                    let mut tail = set.split_off(&value);
+                    let model_tail = model.split_off(&value);
+                    assert!(tail.iter().eq(model_tail.iter()));
+                    assert!(set.iter().eq(model.iter()));
+
+                    set.append(&mut tail);
+                    model.extend(model_tail);
+                    "split off and append"
+                }
+                18 => {
+                    // Only partially consumed, the values it did not get to stay
+                    let divisor = 2 + rng.below(4);
+                    let limit = rng.below(4) as usize;
+                    let matches = |current: &u64| current % divisor == value % divisor;
+
+                    let removed: Vec<_> = set.extract_if(matches).take(limit).collect();
+                    let model_removed: Vec<_> =
+                        model.iter().copied().filter(matches).take(limit).collect();
+                    assert_eq!(removed, model_removed);
+
+                    for removed_value in &model_removed {
+                        model.remove(removed_value);
+                    }
+                    "extract if"
+                }
+                _ => {
+                    let end = value + rng.below(modulo / 4 + 1);
+                    assert!(set.range(value..end).eq(model.range(value..end)));
+                    assert_eq!(set.rank(&value), model.range(..value).count());
+                    "range and rank"
+                }
+            };
+
+            if let Err(error) = set.validate() {
+                panic!("seed {} step {} after {}: {}", seed, step, operation, error);
+            }
+
+            assert_eq!(set.len(), model.len());
+            assert!(set.iter().eq(model.iter()));
+        }
+    }
+
+    #[test]
+    fn model_small_values() {
+        for seed in 0..20 {
+            run_model(seed, 2000, 64);
+        }
+    }
+
+    #[test]
+    fn model_large_values() {
+        for seed in 100..103 {
+            run_model(seed, 5000, 2000);
+        }
+    }
+}
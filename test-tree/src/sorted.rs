@@ -0,0 +1,157 @@
+// Parts of the set API that only depend on the values being sorted, written
+// once for the boxed and the arena set. A set using them has a `cmp` field,
+// `iter` and `len`, and searches its own kind of nodes with `partition_point`
+// and `partition_bounds` methods that work like the ones in `node`.
+
+// Order statistics and nearest values around a probe
+macro_rules! impl_sorted_lookups {
+    ($set:ident) => {
+        // The anonymous constant only scopes the imports
+        const _: () = {
+            use std::borrow::Borrow;
+            use std::cmp::Ordering;
+            use std::ops::RangeBounds;
+
+            use $crate::compare::{assert_range, is_after, is_before, Compare};
+
+            impl<T, C: Compare<T>> $set<T, C> {
+                // Number of values less than the given one, whether it is in the
+                // set or not
+                pub fn rank<Q>(&self, value: &Q) -> usize
+                where
+                    T: Borrow<Q>,
+                    Q: ?Sized,
+                    C: Compare<Q>,
+                {
+                    self.partition_point(|current_value| {
+                        self.cmp.compare(current_value.borrow(), value) == Ordering::Less
+                    })
+                }
+
+                // Greatest value less than or equal to the given one
+                pub fn floor<Q>(&self, value: &Q) -> Option<&T>
+                where
+                    T: Borrow<Q>,
+                    Q: ?Sized,
+                    C: Compare<Q>,
+                {
+                    self.partition_bounds(|current_value| {
+                        self.cmp.compare(current_value.borrow(), value) != Ordering::Greater
+                    })
+                    .0
+                }
+
+                // Least value greater than or equal to the given one
+                pub fn ceiling<Q>(&self, value: &Q) -> Option<&T>
+                where
+                    T: Borrow<Q>,
+                    Q: ?Sized,
+                    C: Compare<Q>,
+                {
+                    self.partition_bounds(|current_value| {
+                        self.cmp.compare(current_value.borrow(), value) == Ordering::Less
+                    })
+                    .1
+                }
+
+                // Greatest value strictly less than the given one
+                pub fn predecessor<Q>(&self, value: &Q) -> Option<&T>
+                where
+                    T: Borrow<Q>,
+                    Q: ?Sized,
+                    C: Compare<Q>,
+                {
+                    self.partition_bounds(|current_value| {
+                        self.cmp.compare(current_value.borrow(), value) == Ordering::Less
+                    })
+                    .0
+                }
+
+                // Least value strictly greater than the given one
+                pub fn successor<Q>(&self, value: &Q) -> Option<&T>
+                where
+                    T: Borrow<Q>,
+                    Q: ?Sized,
+                    C: Compare<Q>,
+                {
+                    self.partition_bounds(|current_value| {
+                        self.cmp.compare(current_value.borrow(), value) != Ordering::Greater
+                    })
+                    .1
+                }
+
+                // Positions of the first value after the lower bound and past the
+                // last value before the upper bound
+                fn range_positions<Q, R>(&self, range: &R) -> (usize, usize)
+                where
+                    T: Borrow<Q>,
+                    Q: ?Sized,
+                    C: Compare<Q>,
+                    R: RangeBounds<Q>,
+                {
+                    let (lower, upper) = (range.start_bound(), range.end_bound());
+
+                    assert_range(&self.cmp, lower, upper, stringify!($set));
+
+                    let front =
+                        self.partition_point(|value| !is_after(&self.cmp, value.borrow(), lower));
+                    let back =
+                        self.partition_point(|value| is_before(&self.cmp, value.borrow(), upper));
+
+                    (front, back)
+                }
+            }
+        };
+    };
+}
+
+// Sets are compared and hashed by their values in sorted order. A set that can
+// tell cheaply that two sets hold the same tree passes that check along, it
+// then short cuts equality.
+macro_rules! impl_value_order {
+    ($set:ident) => {
+        impl_value_order!($set, |_, _| false);
+    };
+
+    ($set:ident, $same_tree:expr) => {
+        const _: () = {
+            use std::cmp::Ordering;
+            use std::hash::{Hash, Hasher};
+
+            use $crate::compare::Compare;
+
+            impl<T: PartialEq, C: Compare<T>> PartialEq for $set<T, C> {
+                fn eq(&self, other: &Self) -> bool {
+                    let same_tree: fn(&Self, &Self) -> bool = $same_tree;
+
+                    same_tree(self, other)
+                        || self.len() == other.len() && self.iter().eq(other.iter())
+                }
+            }
+
+            impl<T: Eq, C: Compare<T>> Eq for $set<T, C> {}
+
+            impl<T: PartialOrd, C: Compare<T>> PartialOrd for $set<T, C> {
+                fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                    self.iter().partial_cmp(other.iter())
+                }
+            }
+
+            impl<T: Ord, C: Compare<T>> Ord for $set<T, C> {
+                fn cmp(&self, other: &Self) -> Ordering {
+                    self.iter().cmp(other.iter())
+                }
+            }
+
+            impl<T: Hash, C: Compare<T>> Hash for $set<T, C> {
+                fn hash<H: Hasher>(&self, state: &mut H) {
+                    self.len().hash(state);
+
+                    for value in self.iter() {
+                        value.hash(state);
+                    }
+                }
+            }
+        };
+    };
+}
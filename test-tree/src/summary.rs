@@ -0,0 +1,21 @@
+// Value cached by every node of an augmented tree for its whole subtree, like
+// a sum or a maximum. `combine` must be associative and have `empty` as its
+// identity, so that the summary of a run of values does not depend on how the
+// tree happens to be shaped around it.
+pub trait Summary<T: ?Sized>: Clone {
+    fn empty() -> Self;
+
+    fn of(value: &T) -> Self;
+
+    // Summary of a run of values followed by another run
+    fn combine(&self, other: &Self) -> Self;
+}
+
+// Trees without augmentation cache nothing
+impl<T: ?Sized> Summary<T> for () {
+    fn empty() -> Self {}
+
+    fn of(_: &T) -> Self {}
+
+    fn combine(&self, _: &Self) -> Self {}
+}
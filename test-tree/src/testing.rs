@@ -0,0 +1,25 @@
+// Helpers shared by the randomized tests
+
+// Linear congruential generator, good enough to drive the randomized tests and
+// reproducible from the seed in a failure message
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+    pub(crate) fn below(&mut self, bound: u64) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+
+        (self.0 >> 33) % bound
+    }
+}
+
+// Pseudo random values below the bound, so that the trees get irregular shapes
+pub(crate) fn values(seed: u64, count: usize, bound: u32) -> Vec<u32> {
+    let mut rng = Rng(seed);
+
+    (0..count)
+        .map(|_| rng.below(u64::from(bound)) as u32)
+        .collect()
+}